@@ -0,0 +1,84 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use image::DynamicImage;
+use kachaka_api::kachaka_api::RosImage;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts allocations, so that the benchmark can check how often a conversion allocates.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const WIDTH: u32 = 1280;
+const HEIGHT: u32 = 720;
+
+fn ros_image(encoding: &str, bytes_per_pixel: u32, padding: u32) -> RosImage {
+    let step = WIDTH * bytes_per_pixel + padding;
+    RosImage {
+        header: None,
+        height: HEIGHT,
+        width: WIDTH,
+        encoding: encoding.to_string(),
+        is_bigendian: false,
+        step,
+        data: (0..step * HEIGHT).map(|i| i as u8).collect(),
+    }
+}
+
+fn allocations_per_conversion(image: &RosImage) -> usize {
+    let image = image.clone();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let converted = DynamicImage::from(image);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(converted);
+    allocations
+}
+
+fn bench_ros_image(c: &mut Criterion) {
+    let images = [
+        ros_image("rgb8", 3, 0),
+        ros_image("bgr8", 3, 0),
+        ros_image("bgr8", 3, 64),
+        ros_image("bgra8", 4, 0),
+        ros_image("mono8", 1, 0),
+        ros_image("16UC1", 2, 0),
+        ros_image("32FC1", 4, 0),
+        ros_image("yuv422", 2, 0),
+    ];
+
+    let bgr8 = &images[1];
+    let bgr8_allocations = allocations_per_conversion(bgr8);
+    println!("bgr8: {} allocation(s) per frame", bgr8_allocations);
+    assert_eq!(bgr8_allocations, 0, "bgr8 should be converted in place");
+
+    let mut group = c.benchmark_group("ros_image");
+    for image in &images {
+        let name = format!("{} step={}", image.encoding, image.step);
+        group.bench_function(name, |b| {
+            b.iter_batched(|| image.clone(), DynamicImage::from, BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_ros_image);
+criterion_main!(benches);
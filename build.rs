@@ -1,8 +1,35 @@
 fn main() {
-    let _res = tonic_build::configure().build_server(false).compile_protos(
-        &["kachaka-api/protos/kachaka-api.proto"],
-        &["kachaka-api/protos"],
-    );
+    let _res = tonic_build::configure()
+        .build_server(true)
+        .generate_default_stubs(true)
+        .type_attribute(
+            ".",
+            "#[cfg_attr(feature = \"serde\", derive(serde::Serialize, serde::Deserialize))]",
+        )
+        .type_attribute(
+            ".kachaka_api.Pose",
+            "#[cfg_attr(feature = \"openapi\", derive(utoipa::ToSchema), schema(as = MapPose))]",
+        )
+        .type_attribute(
+            ".kachaka_api.ShelfSize",
+            "#[cfg_attr(feature = \"openapi\", derive(utoipa::ToSchema))]",
+        )
+        .type_attribute(
+            ".kachaka_api.Location",
+            "#[cfg_attr(feature = \"openapi\", derive(utoipa::ToSchema))]",
+        )
+        .type_attribute(
+            ".kachaka_api.Shelf",
+            "#[cfg_attr(feature = \"openapi\", derive(utoipa::ToSchema))]",
+        )
+        .type_attribute(
+            ".kachaka_api.LockOnEnd",
+            "#[cfg_attr(feature = \"openapi\", derive(utoipa::ToSchema))]",
+        )
+        .compile_protos(
+            &["kachaka-api/protos/kachaka-api.proto"],
+            &["kachaka-api/protos"],
+        );
 
     println!("{:?}", _res);
 }
@@ -0,0 +1,64 @@
+use futures::stream::StreamExt;
+use kachaka_api::battery::{BatteryEvent, BatteryMonitor, BatteryPolicy};
+use kachaka_api::testing::simulator::{Simulator, SimulatorConfig};
+use kachaka_api::testing::FakeKachaka;
+use kachaka_api::types::PowerSupplyStatus;
+use kachaka_api::{BatteryInfo, Command, Pose, StartCommandOptions};
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() {
+    let fake = FakeKachaka::new();
+    fake.set_robot_pose(Pose::new(3.0, 0.0, 0.0));
+    fake.set_battery_info(BatteryInfo {
+        power_supply_status: PowerSupplyStatus::Discharging,
+        remaining_percentage: 24.0,
+    });
+    let simulator = Simulator::new(
+        fake.clone(),
+        SimulatorConfig {
+            time_scale: 10.0,
+            idle_discharge_rate: 0.05,
+            moving_discharge_rate: 0.1,
+            charge_rate: 0.5,
+            ..Default::default()
+        },
+    )
+    .spawn();
+    let client = fake.connect_in_memory().await.unwrap();
+
+    let monitor = BatteryMonitor::spawn(client, Some(BatteryPolicy::default()));
+    let mut events = monitor.events();
+    let mut status_interval = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            Some(event) = events.next() => {
+                println!("{:?}", event);
+                if let BatteryEvent::ChargeStarted(_) = event {
+                    break;
+                }
+            }
+            _ = status_interval.tick() => {
+                if let Some(status) = monitor.status() {
+                    println!(
+                        "{:.1}% ({:?}), time to empty: {:?}",
+                        status.battery_info.remaining_percentage,
+                        status.battery_info.power_supply_status,
+                        status.time_to_empty
+                    );
+                }
+            }
+        }
+    }
+    let result = monitor
+        .start_command(
+            Command::Speak {
+                text: "充電中です".to_string(),
+            },
+            StartCommandOptions::default(),
+        )
+        .await;
+    println!("speak: {:?}", result);
+    println!("charge sessions: {:?}", monitor.charge_sessions());
+    simulator.abort();
+}
@@ -0,0 +1,25 @@
+use futures::stream::StreamExt;
+use image::GenericImageView;
+use kachaka_api::{Camera, FrameDecoding, KachakaApiClient};
+
+#[tokio::main]
+async fn main() {
+    let mut client = KachakaApiClient::connect("http://kachaka-020.local:26400")
+        .await
+        .unwrap();
+
+    let mut frames = Box::pin(
+        client
+            .watch_camera_frames(Camera::Front, FrameDecoding::Blocking)
+            .await,
+    );
+    while let Some(Ok(frame)) = frames.next().await {
+        // the frame stamp tells how old the image is compared to this pose
+        let pose = client.get_latest_robot_pose().await.unwrap();
+        let (width, height) = frame.image().unwrap().dimensions();
+        println!(
+            "{} {} {} {}x{} at ({:.2}, {:.2})",
+            frame.stamp_nsec, frame.frame_id, frame.encoding, width, height, pose.x, pose.y
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use futures::stream::StreamExt;
+use kachaka_api::dispatcher::{DefaultCostFunction, DispatchEvent, Dispatcher, Task};
+use kachaka_api::fleet::Fleet;
+use kachaka_api::kachaka_api::{Location, Pose as LayoutPose, Shelf};
+use kachaka_api::testing::simulator::{Simulator, SimulatorConfig};
+use kachaka_api::testing::FakeKachaka;
+use kachaka_api::types::PowerSupplyStatus;
+use kachaka_api::{BatteryInfo, Command, Pose};
+use std::sync::Arc;
+
+fn layout_pose(x: f64, y: f64) -> Option<LayoutPose> {
+    Some(LayoutPose { x, y, theta: 0.0 })
+}
+
+#[tokio::main]
+async fn main() {
+    // Three simulated robots sharing one layout: the nearest one has a low battery.
+    let robot_poses = [(-3.0, 0.0, 80.0), (0.5, 0.0, 10.0), (3.0, 0.0, 90.0)];
+    let mut robots = Vec::new();
+    for (index, (x, y, battery)) in robot_poses.into_iter().enumerate() {
+        let fake = FakeKachaka::new();
+        fake.set_locations(vec![Location {
+            id: "L01".to_string(),
+            name: "キッチン".to_string(),
+            pose: layout_pose(0.0, 3.0),
+            ..Default::default()
+        }]);
+        fake.set_shelves(vec![
+            Shelf {
+                id: "S01".to_string(),
+                name: "本棚".to_string(),
+                pose: layout_pose(1.0, 0.0),
+                ..Default::default()
+            },
+            Shelf {
+                id: "S02".to_string(),
+                name: "ワゴン".to_string(),
+                pose: layout_pose(-2.0, 0.0),
+                ..Default::default()
+            },
+        ]);
+        fake.set_robot_pose(Pose { x, y, theta: 0.0 });
+        fake.set_battery_info(BatteryInfo {
+            power_supply_status: PowerSupplyStatus::Discharging,
+            remaining_percentage: battery,
+        });
+        let config = SimulatorConfig {
+            time_scale: 20.0,
+            ..Default::default()
+        };
+        Simulator::new(fake.clone(), config).spawn();
+        let client = fake.connect_in_memory().await.unwrap();
+        robots.push((format!("kachaka-{}", index + 1), client));
+    }
+    let fleet = Arc::new(Fleet::new(robots));
+    // Let the fleet receive the first state of every robot.
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+
+    let dispatcher = Dispatcher::new(fleet, DefaultCostFunction::default());
+    let mut events = dispatcher.events();
+    for shelf_id in ["S01", "S02"] {
+        dispatcher.submit(Task::new(Command::MoveShelf {
+            shelf_id: shelf_id.to_string(),
+            location_id: "L01".to_string(),
+        }));
+    }
+
+    let mut finished = 0;
+    while let Some(event) = events.next().await {
+        println!("{:?}", event);
+        if matches!(
+            event,
+            DispatchEvent::Completed { .. } | DispatchEvent::Abandoned { .. }
+        ) {
+            finished += 1;
+            if finished == 2 {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use futures::stream::StreamExt;
+use kachaka_api::mission::{Mission, MissionStep};
+use kachaka_api::testing::FakeKachaka;
+use kachaka_api::{Command, KachakaError, Pose};
+
+#[tokio::main]
+async fn main() {
+    let fake = FakeKachaka::new();
+    let mut client = fake.connect_in_memory().await.unwrap();
+
+    let mut pose_stream = client.watch_robot_pose().await;
+    println!("initial pose: {:?}", pose_stream.next().await);
+    fake.set_robot_pose(Pose {
+        x: 1.0,
+        y: 2.0,
+        theta: 0.5,
+    });
+    println!("updated pose: {:?}", pose_stream.next().await);
+
+    // The second step fails once and succeeds on retry.
+    fake.set_auto_complete(false);
+    let mission = Mission::new()
+        .step(MissionStep::new(Command::Speak {
+            text: "こんにちは".to_string(),
+        }))
+        .step(MissionStep::new(Command::ReturnHome).max_retries(1));
+    let mut events = mission.run(client.clone()).await;
+
+    let mut running_command = fake.watch_running_command();
+    let mut results = vec![Ok(()), Err(KachakaError { error_code: 1 }), Ok(())].into_iter();
+    let driver = tokio::spawn({
+        let fake = fake.clone();
+        async move {
+            while running_command.changed().await.is_ok() {
+                let running = running_command.borrow_and_update().clone();
+                if running.is_some() {
+                    fake.complete_command(results.next().unwrap());
+                }
+            }
+        }
+    });
+
+    while let Some(event) = events.next().await {
+        println!("{:?}", event);
+    }
+    driver.abort();
+
+    for started in fake.started_commands() {
+        println!("started: {} {:?}", started.command_id, started.command);
+    }
+}
@@ -0,0 +1,54 @@
+use futures::stream::StreamExt;
+use kachaka_api::fleet::Fleet;
+use kachaka_api::testing::FakeKachaka;
+use kachaka_api::{Command, KachakaError, Pose, StartCommandOptions};
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() {
+    // With real robots: `Fleet::load("fleet.toml")`.
+    let fakes = [FakeKachaka::new(), FakeKachaka::new()];
+    let mut robots = Vec::new();
+    for (index, fake) in fakes.iter().enumerate() {
+        let client = fake.connect_in_memory().await.unwrap();
+        robots.push((format!("kachaka-{}", index + 1), client));
+    }
+    let fleet = Fleet::new(robots);
+
+    let mut pose_stream = fleet.watch_robot_pose();
+    let mut error_stream = fleet.watch_error();
+    fakes[1].set_robot_pose(Pose {
+        x: 1.0,
+        y: 2.0,
+        theta: 0.0,
+    });
+    fakes[0].set_error(vec![KachakaError { error_code: 14606 }]);
+
+    // The streams carry the latest state, so updates may be coalesced.
+    let _ = tokio::time::timeout(Duration::from_millis(500), async {
+        loop {
+            tokio::select! {
+                Some(update) = pose_stream.next() => {
+                    println!("pose of {}: {:?}", update.robot_id, update.value);
+                }
+                Some(update) = error_stream.next() => {
+                    println!("errors of {}: {:?}", update.robot_id, update.value);
+                }
+            }
+        }
+    })
+    .await;
+
+    let results = fleet
+        .broadcast_command(
+            Command::Speak {
+                text: "こんにちは".to_string(),
+            },
+            StartCommandOptions::default(),
+        )
+        .await;
+    println!("broadcast: {:?}", results);
+    for robot_id in fleet.robot_ids() {
+        println!("health of {}: {:?}", robot_id, fleet.health(&robot_id));
+    }
+}
@@ -0,0 +1,39 @@
+use futures::stream::StreamExt;
+use kachaka_api::mission::{Mission, MissionStep};
+use kachaka_api::{Command, KachakaApiClient, StartCommandOptions};
+
+#[tokio::main]
+async fn main() {
+    let client = KachakaApiClient::connect("http://kachaka-020.local:26400")
+        .await
+        .unwrap();
+
+    let mission = Mission::new()
+        .step(
+            MissionStep::new(Command::MoveShelf {
+                shelf_id: "S01".to_string(),
+                location_id: "L01".to_string(),
+            })
+            .max_retries(1),
+        )
+        .step(MissionStep::new(Command::Speak {
+            text: "棚を持ってきました".to_string(),
+        }))
+        .step(
+            MissionStep::new(Command::Lock { duration_sec: 10.0 })
+                .options(StartCommandOptions::default().title("待機中")),
+        )
+        .step(MissionStep::new(Command::ReturnShelf {
+            shelf_id: "S01".to_string(),
+        }))
+        .step(MissionStep::new(Command::ReturnHome))
+        .on_failure(MissionStep::new(Command::ReturnShelf {
+            shelf_id: "S01".to_string(),
+        }))
+        .on_failure(MissionStep::new(Command::ReturnHome));
+
+    let mut events = mission.run(client).await;
+    while let Some(event) = events.next().await {
+        println!("{:?}", event);
+    }
+}
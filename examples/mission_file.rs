@@ -0,0 +1,32 @@
+use futures::stream::StreamExt;
+use kachaka_api::mission_file::MissionFile;
+use kachaka_api::shelf_location_resolver::ShelfLocationResolver;
+use kachaka_api::KachakaApiClient;
+
+#[tokio::main]
+async fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "examples/missions/bring_shelf.yaml".to_string());
+    let mission_file = MissionFile::load(&path).unwrap();
+
+    let client = KachakaApiClient::connect("http://kachaka-020.local:26400")
+        .await
+        .unwrap();
+
+    let (resolver, _) = ShelfLocationResolver::spawn(client.clone());
+    resolver.wait_until_ready().await;
+
+    let mission = match mission_file.resolve(&resolver).await {
+        Ok(mission) => mission,
+        Err(e) => {
+            eprintln!("invalid mission file: {:?}", e);
+            return;
+        }
+    };
+
+    let mut events = mission.run(client).await;
+    while let Some(event) = events.next().await {
+        println!("{:?}", event);
+    }
+}
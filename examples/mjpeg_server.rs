@@ -0,0 +1,15 @@
+use kachaka_api::mjpeg::MjpegServer;
+use kachaka_api::KachakaApiClient;
+
+#[tokio::main]
+async fn main() {
+    let client = KachakaApiClient::connect("http://kachaka-020.local:26400")
+        .await
+        .unwrap();
+
+    // Open http://localhost:8081/ in a browser.
+    MjpegServer::new(client)
+        .serve("0.0.0.0:8081")
+        .await
+        .unwrap();
+}
@@ -0,0 +1,32 @@
+use kachaka_api::path_planner::{OccupancyGrid, PathPlanner, PlannerConfig};
+use kachaka_api::{KachakaApiClient, Pose};
+
+#[tokio::main]
+async fn main() {
+    let mut client = KachakaApiClient::connect("http://kachaka-020.local:26400")
+        .await
+        .unwrap();
+
+    let map = client.get_latest_png_map().await.unwrap();
+    let planner = PathPlanner::new(
+        OccupancyGrid::from_map(&map).unwrap(),
+        PlannerConfig::default(),
+    );
+    let pose = client.get_latest_robot_pose().await.unwrap();
+
+    for location in client.get_latest_locations().await.unwrap() {
+        let Ok(target) = Pose::try_from(&location) else {
+            continue;
+        };
+        match planner.plan(&pose, &target) {
+            Ok(path) => println!(
+                "{}: {:.2} m, {} waypoints, about {:.0} s",
+                location.name,
+                path.length,
+                path.poses.len(),
+                path.estimated_duration(0.3, 0.8).as_secs_f64()
+            ),
+            Err(error) => println!("{}: {:?}", location.name, error),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use futures::stream::StreamExt;
+use kachaka_api::recording::{RecordTopic, Recorder, Recording, Replayer};
+use kachaka_api::testing::FakeKachaka;
+use kachaka_api::Pose;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() {
+    let path = std::env::temp_dir().join("kachaka_record_replay.rec");
+
+    // Record a fake robot moving along the x axis.
+    let source = FakeKachaka::new();
+    let client = source.connect_in_memory().await.unwrap();
+    let recorder = Recorder::start(
+        &client,
+        &[RecordTopic::RobotPose, RecordTopic::BatteryInfo],
+        &path,
+    )
+    .await
+    .unwrap();
+    for step in 1..=10 {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        source.set_robot_pose(Pose {
+            x: step as f64 * 0.1,
+            y: 0.0,
+            theta: 0.0,
+        });
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    recorder.stop().await.unwrap();
+
+    let recording = Recording::load(&path).unwrap();
+    println!(
+        "recorded {} records over {:?}",
+        recording.records().len(),
+        recording.duration()
+    );
+
+    // Replay at 4x speed, both as a stream and through a gRPC server.
+    let replayer = Replayer::new(recording, 4.0);
+    let target = FakeKachaka::new();
+    let mut client = target.connect_in_memory().await.unwrap();
+    let replay_task = replayer.replay_into(target.clone());
+
+    let mut replayed_pose_stream = Box::pin(replayer.watch_robot_pose());
+    while let Some(pose) = replayed_pose_stream.next().await {
+        println!("replayed pose: {:?}", pose);
+    }
+    replay_task.await.unwrap();
+    println!("served pose: {:?}", client.get_latest_robot_pose().await);
+}
@@ -0,0 +1,24 @@
+use futures::stream::StreamExt;
+use kachaka_api::KachakaApiClient;
+
+const CURSOR_FILE: &str = "last_command_result.cursor";
+
+#[tokio::main]
+async fn main() {
+    let mut client = KachakaApiClient::connect("http://kachaka-020.local:26400")
+        .await
+        .unwrap();
+
+    // start after the last result logged by the previous run
+    let cursor = std::fs::read_to_string(CURSOR_FILE)
+        .ok()
+        .and_then(|content| content.trim().parse().ok())
+        .unwrap_or(0);
+    let mut results = Box::pin(client.watch_last_command_result_from(cursor).await);
+    while let Some(Ok(versioned)) = results.next().await {
+        if let Some(result) = versioned.value {
+            println!("{:?}: {:?}", result.command, result.result);
+        }
+        std::fs::write(CURSOR_FILE, versioned.cursor.to_string()).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+use futures::stream::StreamExt;
+use kachaka_api::robot_state::RobotState;
+use kachaka_api::KachakaApiClient;
+use std::sync::Arc;
+
+#[tokio::main]
+async fn main() {
+    let client = KachakaApiClient::connect("http://kachaka-020.local:26400")
+        .await
+        .unwrap();
+    let state = Arc::new(RobotState::spawn(client));
+
+    let snapshot = state.wait_until_ready().await;
+    println!(
+        "{} locations, {} shelves",
+        snapshot
+            .locations
+            .map_or(0, |locations| locations.value.len()),
+        snapshot.shelves.map_or(0, |shelves| shelves.value.len())
+    );
+
+    // other parts of the app read the same cache without their own subscriptions
+    let reader = state.clone();
+    tokio::spawn(async move {
+        let mut results = Box::pin(reader.watch_last_command_result());
+        while let Some(result) = results.next().await {
+            println!("last command result: {:?}", result.value);
+        }
+    });
+
+    let mut snapshots = Box::pin(state.watch_snapshot());
+    while let Some(snapshot) = snapshots.next().await {
+        if let (Some(pose), Some(battery_info)) = (snapshot.pose, snapshot.battery_info) {
+            println!(
+                "({:.2}, {:.2}) {:.0}% {:?}",
+                pose.value.x,
+                pose.value.y,
+                battery_info.value.remaining_percentage,
+                snapshot
+                    .command_state
+                    .map(|command_state| command_state.value)
+            );
+        }
+    }
+}
@@ -8,21 +8,26 @@ async fn main() {
         .await
         .unwrap();
 
-    let resolver = ShelfLocationResolver::new(client.clone());
+    let (resolver, update_loop) = ShelfLocationResolver::spawn(client.clone());
+    let mut changes = resolver.watch_changes();
     let mut moving_shelf_id_stream = client.watch_moving_shelf_id().await;
+    resolver.wait_until_ready().await;
+    println!("shelves: {:?}", resolver.get_all_shelves().await);
 
-    tokio::join! {
-        async {
-            resolver.run_update_loop().await
-        },
-        async {
-            while let Some(Ok(moving_shelf_id)) = moving_shelf_id_stream.next().await {
+    loop {
+        tokio::select! {
+            Some(change) = changes.next() => {
+                println!("layout changed: {:?}", change);
+            }
+            Some(Ok(moving_shelf_id)) = moving_shelf_id_stream.next() => {
                 println!("Moving shelf ID: {}", moving_shelf_id);
                 if let Some(shelf) = resolver.get_shelf_by_id(&moving_shelf_id).await {
                     println!("a.k.a. shelf: {:?}", shelf);
                 }
             }
-            ()
+            _ = tokio::signal::ctrl_c() => break,
         }
-    };
+    }
+    resolver.stop();
+    update_loop.await.unwrap();
 }
@@ -20,6 +20,5 @@ async fn main() {
     // sleep 1 second
     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
 
-    let response = client.cancel_command().await.unwrap();
-    println!("{:?}", response);
+    client.cancel_command().await.unwrap();
 }
@@ -0,0 +1,74 @@
+use futures::stream::StreamExt;
+use kachaka_api::kachaka_api::{Location, Pose as LayoutPose, Shelf};
+use kachaka_api::mission::{Mission, MissionEvent, MissionStep};
+use kachaka_api::testing::simulator::{Simulator, SimulatorConfig};
+use kachaka_api::testing::FakeKachaka;
+use kachaka_api::Command;
+
+#[tokio::main]
+async fn main() {
+    let fake = FakeKachaka::new();
+    fake.set_locations(vec![Location {
+        id: "L01".to_string(),
+        name: "キッチン".to_string(),
+        pose: Some(LayoutPose {
+            x: 2.0,
+            y: 1.0,
+            theta: std::f64::consts::FRAC_PI_2,
+        }),
+        ..Default::default()
+    }]);
+    fake.set_shelves(vec![Shelf {
+        id: "S01".to_string(),
+        name: "本棚".to_string(),
+        pose: Some(LayoutPose {
+            x: -1.0,
+            y: 0.0,
+            theta: 0.0,
+        }),
+        ..Default::default()
+    }]);
+
+    let simulator = Simulator::new(
+        fake.clone(),
+        SimulatorConfig {
+            time_scale: 20.0,
+            ..Default::default()
+        },
+    )
+    .spawn();
+
+    let mut client = fake.connect_in_memory().await.unwrap();
+    let mut pose_stream = client.watch_robot_pose().await;
+    let mut battery_stream = client.watch_battery_info().await;
+
+    let mission = Mission::new()
+        .step(MissionStep::new(Command::MoveShelf {
+            shelf_id: "S01".to_string(),
+            location_id: "L01".to_string(),
+        }))
+        .step(MissionStep::new(Command::ReturnShelf {
+            shelf_id: "S01".to_string(),
+        }))
+        .step(MissionStep::new(Command::ReturnHome));
+    let mut events = mission.run(client.clone()).await;
+
+    loop {
+        tokio::select! {
+            Some(event) = events.next() => {
+                println!("{:?}", event);
+                if matches!(event, MissionEvent::Completed | MissionEvent::Aborted { .. }) {
+                    break;
+                }
+            }
+            Some(Ok(pose)) = pose_stream.next() => {
+                println!("pose: ({:.2}, {:.2}, {:.2})", pose.x, pose.y, pose.theta);
+            }
+            Some(Ok(battery_info)) = battery_stream.next() => {
+                println!("battery: {:?}", battery_info);
+            }
+        }
+    }
+    println!("shelves: {:?}", client.get_latest_shelves().await.unwrap());
+    simulator.abort();
+}
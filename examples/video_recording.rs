@@ -0,0 +1,34 @@
+use kachaka_api::video::{VideoReader, VideoRecorder, VideoWriterConfig};
+use kachaka_api::{Camera, KachakaApiClient};
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() {
+    let client = KachakaApiClient::connect("http://kachaka-020.local:26400")
+        .await
+        .unwrap();
+
+    let config = VideoWriterConfig::new("videos")
+        .prefix("front")
+        .max_segment_duration(Some(Duration::from_secs(5)));
+    let recorder = VideoRecorder::start(&client, Camera::Front, config).unwrap();
+    tokio::time::sleep(Duration::from_secs(12)).await;
+    let segments = recorder.stop().await.unwrap();
+    println!("segments: {:?}", segments);
+
+    let mut reader = VideoReader::open("videos", "front").unwrap();
+    println!(
+        "{} frames, time range: {:?}",
+        reader.frame_count(),
+        reader.time_range()
+    );
+    let first_frame = reader.frames().next();
+    if let Some(frame) = first_frame {
+        frame
+            .unwrap()
+            .decode()
+            .unwrap()
+            .save("first_frame.png")
+            .unwrap();
+    }
+}
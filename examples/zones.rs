@@ -0,0 +1,40 @@
+use futures::stream::StreamExt;
+use kachaka_api::testing::simulator::{Simulator, SimulatorConfig};
+use kachaka_api::testing::FakeKachaka;
+use kachaka_api::zones::{ZoneEvent, ZoneSet};
+use kachaka_api::{Pose, StartCommandOptions};
+
+#[tokio::main]
+async fn main() {
+    let zone_set =
+        ZoneSet::load(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/zones.yaml")).unwrap();
+
+    let fake = FakeKachaka::new();
+    let simulator = Simulator::new(fake.clone(), SimulatorConfig::default()).spawn();
+    let mut client = fake.connect_in_memory().await.unwrap();
+
+    let mut zone_events = Box::pin(zone_set.watch_zone_events(&mut client).await);
+    client
+        .move_to_pose(&Pose::new(6.0, 0.0, 0.0), StartCommandOptions::default())
+        .await
+        .unwrap();
+
+    while let Some(Ok(event)) = zone_events.next().await {
+        println!("{:?}", event);
+        match event {
+            ZoneEvent::Entered { zone, .. } if zone == "kitchen" => {
+                client
+                    .speak("キッチンに入りました", StartCommandOptions::default())
+                    .await
+                    .unwrap();
+            }
+            ZoneEvent::Entered { zone, .. } if zone == "no-go" => {
+                client.cancel_command().await.unwrap();
+                println!("alarm: entered the no-go area");
+                break;
+            }
+            _ => {}
+        }
+    }
+    simulator.abort();
+}
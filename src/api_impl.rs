@@ -1,10 +1,13 @@
 use std::collections::HashMap;
 
-use crate::types::{BatteryInfo, CommandResult, CommandState, KachakaError, Pose};
+use crate::camera::{CameraFrame, FrameDecoding};
+use crate::types::{
+    BatteryInfo, Camera, Command, CommandResult, CommandState, KachakaError, Pose, Versioned,
+};
 use crate::KachakaApiError;
 use crate::{kachaka_api, StartCommandOptions};
 
-use futures::stream::Stream;
+use futures::stream::{Stream, StreamExt};
 use image::DynamicImage;
 use kachaka_api::kachaka_api_client::KachakaApiClient as TonicKachakaApiClient;
 use tokio::sync::mpsc;
@@ -42,9 +45,76 @@ fn parse_getter_response<T>(
     }
 }
 
+fn response_cursor(metadata: Option<kachaka_api::Metadata>, cursor: i64) -> i64 {
+    let new_cursor = metadata.unwrap().cursor;
+    #[cfg(feature = "tracing")]
+    {
+        tracing::Span::current().record("new_cursor", new_cursor);
+        if new_cursor < cursor {
+            tracing::warn!(cursor, new_cursor, "cursor reset");
+        } else if cursor != 0 && new_cursor != cursor {
+            tracing::debug!(cursor, new_cursor, "cursor jumped");
+        }
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = cursor;
+    new_cursor
+}
+
+fn spawn_watch<F>(rpc_name: &'static str, future: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    #[cfg(feature = "tracing")]
+    let future = tracing::Instrument::instrument(future, tracing::info_span!("watch", rpc_name));
+    #[cfg(not(feature = "tracing"))]
+    let _ = rpc_name;
+    tokio::spawn(future);
+}
+
+/// Watches a getter from `cursor` on, sending each value with its new cursor.
+fn spawn_versioned_watch<T, F, Fut>(
+    rpc_name: &'static str,
+    client: &TonicKachakaApiClient<Channel>,
+    mut cursor: i64,
+    mut get: F,
+) -> impl Stream<Item = Result<Versioned<T>, KachakaApiError>>
+where
+    T: Send + 'static,
+    F: FnMut(TonicKachakaApiClient<Channel>, i64) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<(i64, T), KachakaApiError>> + Send,
+{
+    let (tx, rx) = mpsc::unbounded_channel();
+    let client = client.clone();
+    spawn_watch(rpc_name, async move {
+        loop {
+            let result = get(client.clone(), cursor)
+                .await
+                .map(|(new_cursor, value)| {
+                    cursor = new_cursor;
+                    Versioned::new(value, new_cursor)
+                });
+            if tx.send(result).is_err() {
+                break;
+            }
+        }
+    });
+    UnboundedReceiverStream::new(rx)
+}
+
 // getter api
 
 // GetRobotSerialNumber
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetRobotSerialNumber",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_robot_serial_number_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -53,8 +123,12 @@ async fn get_robot_serial_number_with_cursor(
         metadata: Some(kachaka_api::Metadata { cursor }),
     });
     let response = client.get_robot_serial_number(request).await;
-    parse_getter_response(response)
-        .map(|response| (response.metadata.unwrap().cursor, response.serial_number))
+    parse_getter_response(response).map(|response| {
+        (
+            response_cursor(response.metadata, cursor),
+            response.serial_number,
+        )
+    })
 }
 
 pub async fn get_robot_serial_number(
@@ -72,29 +146,48 @@ pub async fn get_latest_robot_serial_number(
     get_robot_serial_number(client, 0).await
 }
 
+pub async fn get_robot_serial_number_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<String>, KachakaApiError> {
+    get_robot_serial_number_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_robot_serial_number(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<String, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<String, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_robot_serial_number_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, serial_number)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(serial_number)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
-    UnboundedReceiverStream::new(rx)
+    watch_robot_serial_number_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_robot_serial_number_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<String>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetRobotSerialNumber",
+        client,
+        cursor,
+        |mut client, cursor| async move {
+            get_robot_serial_number_with_cursor(&mut client, cursor).await
+        },
+    )
 }
 
 // GetRobotVersion
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetRobotVersion",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_robot_version_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -104,7 +197,7 @@ async fn get_robot_version_with_cursor(
     });
     let response = client.get_robot_version(request).await;
     parse_getter_response(response)
-        .map(|response| (response.metadata.unwrap().cursor, response.version))
+        .map(|response| (response_cursor(response.metadata, cursor), response.version))
 }
 
 pub async fn get_robot_version(
@@ -122,29 +215,46 @@ pub async fn get_latest_robot_version(
     get_robot_version(client, 0).await
 }
 
+pub async fn get_robot_version_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<String>, KachakaApiError> {
+    get_robot_version_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_robot_version(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<String, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<String, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_robot_version_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, version)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(version)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
-    UnboundedReceiverStream::new(rx)
+    watch_robot_version_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_robot_version_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<String>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetRobotVersion",
+        client,
+        cursor,
+        |mut client, cursor| async move { get_robot_version_with_cursor(&mut client, cursor).await },
+    )
 }
 
 // GetRobotPose
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetRobotPose",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_robot_pose_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -155,7 +265,7 @@ async fn get_robot_pose_with_cursor(
     let response = client.get_robot_pose(request).await;
     let pose_result = parse_getter_response(response)?;
     if let Some(pose) = pose_result.pose {
-        Ok((pose_result.metadata.unwrap().cursor, pose.into()))
+        Ok((response_cursor(pose_result.metadata, cursor), pose.into()))
     } else {
         Err(KachakaApiError::NullResult)
     }
@@ -176,29 +286,46 @@ pub async fn get_latest_robot_pose(
     get_robot_pose(client, 0).await
 }
 
+pub async fn get_robot_pose_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<Pose>, KachakaApiError> {
+    get_robot_pose_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_robot_pose(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<Pose, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<Pose, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_robot_pose_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, pose)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(pose)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
-    UnboundedReceiverStream::new(rx)
+    watch_robot_pose_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_robot_pose_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<Pose>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetRobotPose",
+        client,
+        cursor,
+        |mut client, cursor| async move { get_robot_pose_with_cursor(&mut client, cursor).await },
+    )
 }
 
 // GetBatteryInfo
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetBatteryInfo",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_battery_info_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -209,7 +336,7 @@ async fn get_battery_info_with_cursor(
     let response = client.get_battery_info(request).await;
     parse_getter_response(response).map(|response| {
         (
-            response.metadata.unwrap().cursor,
+            response_cursor(response.metadata, cursor),
             BatteryInfo {
                 power_supply_status: response.power_supply_status.into(),
                 remaining_percentage: response.remaining_percentage,
@@ -233,29 +360,46 @@ pub async fn get_latest_battery_info(
     get_battery_info(client, 0).await
 }
 
+pub async fn get_battery_info_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<BatteryInfo>, KachakaApiError> {
+    get_battery_info_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_battery_info(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<BatteryInfo, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<BatteryInfo, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_battery_info_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, battery_info)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(battery_info)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
-    UnboundedReceiverStream::new(rx)
+    watch_battery_info_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_battery_info_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<BatteryInfo>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetBatteryInfo",
+        client,
+        cursor,
+        |mut client, cursor| async move { get_battery_info_with_cursor(&mut client, cursor).await },
+    )
 }
 
 // GetFrontCameraRosImage
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetFrontCameraRosImage",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_front_camera_ros_image_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -266,7 +410,7 @@ async fn get_front_camera_ros_image_with_cursor(
     let response = client.get_front_camera_ros_image(request).await;
     parse_getter_response(response).map(|response| {
         (
-            response.metadata.unwrap().cursor,
+            response_cursor(response.metadata, cursor),
             DynamicImage::from(response.image.unwrap()),
         )
     })
@@ -287,29 +431,48 @@ pub async fn get_latest_front_camera_ros_image(
     get_front_camera_ros_image(client, 0).await
 }
 
+pub async fn get_front_camera_ros_image_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<DynamicImage>, KachakaApiError> {
+    get_front_camera_ros_image_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_front_camera_ros_image(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<DynamicImage, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<DynamicImage, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_front_camera_ros_image_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, image)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(image)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
-    UnboundedReceiverStream::new(rx)
+    watch_front_camera_ros_image_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_front_camera_ros_image_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<DynamicImage>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetFrontCameraRosImage",
+        client,
+        cursor,
+        |mut client, cursor| async move {
+            get_front_camera_ros_image_with_cursor(&mut client, cursor).await
+        },
+    )
 }
 
 // GetFrontCameraRosCompressedImage
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetFrontCameraRosCompressedImage",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_front_camera_ros_compressed_image_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -320,7 +483,7 @@ async fn get_front_camera_ros_compressed_image_with_cursor(
     let response = client.get_front_camera_ros_compressed_image(request).await;
     parse_getter_response(response).map(|response| {
         (
-            response.metadata.unwrap().cursor,
+            response_cursor(response.metadata, cursor),
             DynamicImage::from(response.image.unwrap()),
         )
     })
@@ -341,30 +504,48 @@ pub async fn get_latest_front_camera_ros_compressed_image(
     get_front_camera_ros_compressed_image(client, 0).await
 }
 
+pub async fn get_front_camera_ros_compressed_image_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<DynamicImage>, KachakaApiError> {
+    get_front_camera_ros_compressed_image_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_front_camera_ros_compressed_image(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<DynamicImage, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<DynamicImage, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_front_camera_ros_compressed_image_with_cursor(&mut client_clone, cursor).await
-            {
-                Ok((new_cursor, image)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(image)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
-    UnboundedReceiverStream::new(rx)
+    watch_front_camera_ros_compressed_image_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_front_camera_ros_compressed_image_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<DynamicImage>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetFrontCameraRosCompressedImage",
+        client,
+        cursor,
+        |mut client, cursor| async move {
+            get_front_camera_ros_compressed_image_with_cursor(&mut client, cursor).await
+        },
+    )
 }
 
 // GetBackCameraRosImage
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetBackCameraRosImage",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_back_camera_ros_image_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -375,7 +556,7 @@ async fn get_back_camera_ros_image_with_cursor(
     let response = client.get_back_camera_ros_image(request).await;
     parse_getter_response(response).map(|response| {
         (
-            response.metadata.unwrap().cursor,
+            response_cursor(response.metadata, cursor),
             DynamicImage::from(response.image.unwrap()),
         )
     })
@@ -396,29 +577,48 @@ pub async fn get_latest_back_camera_ros_image(
     get_back_camera_ros_image(client, 0).await
 }
 
+pub async fn get_back_camera_ros_image_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<DynamicImage>, KachakaApiError> {
+    get_back_camera_ros_image_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_back_camera_ros_image(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<DynamicImage, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<DynamicImage, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_back_camera_ros_image_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, image)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(image)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
-    UnboundedReceiverStream::new(rx)
+    watch_back_camera_ros_image_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_back_camera_ros_image_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<DynamicImage>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetBackCameraRosImage",
+        client,
+        cursor,
+        |mut client, cursor| async move {
+            get_back_camera_ros_image_with_cursor(&mut client, cursor).await
+        },
+    )
 }
 
 // GetBackCameraRosCompressedImage
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetBackCameraRosCompressedImage",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_back_camera_ros_compressed_image_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -429,7 +629,7 @@ async fn get_back_camera_ros_compressed_image_with_cursor(
     let response = client.get_back_camera_ros_compressed_image(request).await;
     parse_getter_response(response).map(|response| {
         (
-            response.metadata.unwrap().cursor,
+            response_cursor(response.metadata, cursor),
             DynamicImage::from(response.image.unwrap()),
         )
     })
@@ -450,27 +650,158 @@ pub async fn get_latest_back_camera_ros_compressed_image(
     get_back_camera_ros_compressed_image(client, 0).await
 }
 
+pub async fn get_back_camera_ros_compressed_image_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<DynamicImage>, KachakaApiError> {
+    get_back_camera_ros_compressed_image_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_back_camera_ros_compressed_image(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<DynamicImage, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<DynamicImage, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_back_camera_ros_compressed_image_with_cursor(&mut client_clone, cursor).await
-            {
-                Ok((new_cursor, image)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(image)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
+    watch_back_camera_ros_compressed_image_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_back_camera_ros_compressed_image_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<DynamicImage>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetBackCameraRosCompressedImage",
+        client,
+        cursor,
+        |mut client, cursor| async move {
+            get_back_camera_ros_compressed_image_with_cursor(&mut client, cursor).await
+        },
+    )
+}
+
+// compressed camera images without decoding, for recording and streaming
+pub(crate) async fn get_camera_ros_compressed_image_raw_with_cursor(
+    client: &mut TonicKachakaApiClient<Channel>,
+    camera: Camera,
+    cursor: i64,
+) -> Result<(i64, kachaka_api::RosCompressedImage), KachakaApiError> {
+    let request = tonic::Request::new(kachaka_api::GetRequest {
+        metadata: Some(kachaka_api::Metadata { cursor }),
     });
-    UnboundedReceiverStream::new(rx)
+    let response = match camera {
+        Camera::Front => client.get_front_camera_ros_compressed_image(request).await,
+        Camera::Back => client.get_back_camera_ros_compressed_image(request).await,
+    };
+    let image_result = parse_getter_response(response)?;
+    if let Some(image) = image_result.image {
+        Ok((response_cursor(image_result.metadata, cursor), image))
+    } else {
+        Err(KachakaApiError::NullResult)
+    }
+}
+
+pub(crate) fn watch_camera_ros_compressed_image_raw(
+    client: &TonicKachakaApiClient<Channel>,
+    camera: Camera,
+) -> impl Stream<Item = Result<kachaka_api::RosCompressedImage, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetCameraRosCompressedImage",
+        client,
+        0,
+        move |mut client, cursor| async move {
+            get_camera_ros_compressed_image_raw_with_cursor(&mut client, camera, cursor).await
+        },
+    )
+    .map(|result| result.map(|versioned| versioned.value))
+}
+
+// camera frames with their ROS header
+async fn get_camera_ros_image_raw_with_cursor(
+    client: &mut TonicKachakaApiClient<Channel>,
+    camera: Camera,
+    cursor: i64,
+) -> Result<(i64, kachaka_api::RosImage), KachakaApiError> {
+    let request = tonic::Request::new(kachaka_api::GetRequest {
+        metadata: Some(kachaka_api::Metadata { cursor }),
+    });
+    let response = match camera {
+        Camera::Front => client.get_front_camera_ros_image(request).await,
+        Camera::Back => client.get_back_camera_ros_image(request).await,
+    };
+    let image_result = parse_getter_response(response)?;
+    if let Some(image) = image_result.image {
+        Ok((response_cursor(image_result.metadata, cursor), image))
+    } else {
+        Err(KachakaApiError::NullResult)
+    }
+}
+
+pub async fn get_camera_frame(
+    client: &mut TonicKachakaApiClient<Channel>,
+    camera: Camera,
+    cursor: i64,
+    decoding: FrameDecoding,
+) -> Result<CameraFrame, KachakaApiError> {
+    let (_, image) =
+        get_camera_ros_compressed_image_raw_with_cursor(client, camera, cursor).await?;
+    CameraFrame::from_compressed(camera, image)
+        .decode_with(decoding)
+        .await
+}
+
+pub async fn get_latest_camera_frame(
+    client: &mut TonicKachakaApiClient<Channel>,
+    camera: Camera,
+    decoding: FrameDecoding,
+) -> Result<CameraFrame, KachakaApiError> {
+    get_camera_frame(client, camera, 0, decoding).await
+}
+
+pub async fn watch_camera_frames(
+    client: &mut TonicKachakaApiClient<Channel>,
+    camera: Camera,
+    decoding: FrameDecoding,
+) -> impl Stream<Item = Result<CameraFrame, KachakaApiError>> {
+    // decoded by the consumer, so that frames it never takes are never decoded
+    watch_camera_ros_compressed_image_raw(client, camera).then(move |item| async move {
+        CameraFrame::from_compressed(camera, item?)
+            .decode_with(decoding)
+            .await
+    })
+}
+
+pub async fn get_camera_raw_frame(
+    client: &mut TonicKachakaApiClient<Channel>,
+    camera: Camera,
+    cursor: i64,
+) -> Result<CameraFrame, KachakaApiError> {
+    get_camera_ros_image_raw_with_cursor(client, camera, cursor)
+        .await
+        .map(|(_, image)| CameraFrame::from_raw(camera, image))
+}
+
+pub async fn get_latest_camera_raw_frame(
+    client: &mut TonicKachakaApiClient<Channel>,
+    camera: Camera,
+) -> Result<CameraFrame, KachakaApiError> {
+    get_camera_raw_frame(client, camera, 0).await
+}
+
+pub async fn watch_camera_raw_frames(
+    client: &mut TonicKachakaApiClient<Channel>,
+    camera: Camera,
+) -> impl Stream<Item = Result<CameraFrame, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetCameraRosImage",
+        client,
+        0,
+        move |mut client, cursor| async move {
+            get_camera_ros_image_raw_with_cursor(&mut client, camera, cursor).await
+        },
+    )
+    .map(move |result| result.map(|versioned| CameraFrame::from_raw(camera, versioned.value)))
 }
 
 // GetRobotErrorCodeJson
@@ -494,6 +825,10 @@ fn parse_robot_error_code_json(
     Ok(result)
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "GetRobotErrorCodeJson", skip(client), err(Debug))
+)]
 pub async fn get_robot_error_code_json(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> Result<HashMap<i32, HashMap<String, String>>, KachakaApiError> {
@@ -518,6 +853,16 @@ pub async fn get_robot_error_code_json(
 }
 
 // GetError
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetError",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_error_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -528,7 +873,7 @@ async fn get_error_with_cursor(
     let response = client.get_error(request).await;
     parse_getter_response(response).map(|response| {
         (
-            response.metadata.unwrap().cursor,
+            response_cursor(response.metadata, cursor),
             response
                 .error_codes
                 .into_iter()
@@ -555,29 +900,46 @@ pub async fn get_latest_error(
         .map(|(_, errors)| errors)
 }
 
+pub async fn get_error_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<Vec<KachakaError>>, KachakaApiError> {
+    get_error_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_error(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<Vec<KachakaError>, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<Vec<KachakaError>, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_error_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, errors)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(errors)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
-    UnboundedReceiverStream::new(rx)
+    watch_error_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_error_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<Vec<KachakaError>>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetError",
+        client,
+        cursor,
+        |mut client, cursor| async move { get_error_with_cursor(&mut client, cursor).await },
+    )
 }
 
 // GetCommandState
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetCommandState",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_command_state_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -587,7 +949,7 @@ async fn get_command_state_with_cursor(
     });
     let response = client.get_command_state(request).await;
     parse_getter_response(response)
-        .map(|response| (response.metadata.unwrap().cursor, response.into()))
+        .map(|response| (response_cursor(response.metadata, cursor), response.into()))
 }
 
 pub async fn get_command_state(
@@ -605,29 +967,46 @@ pub async fn get_latest_command_state(
     get_command_state(client, 0).await
 }
 
+pub async fn get_command_state_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<CommandState>, KachakaApiError> {
+    get_command_state_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_command_state(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<CommandState, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<CommandState, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_command_state_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, command_state)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(command_state)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
-    UnboundedReceiverStream::new(rx)
+    watch_command_state_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_command_state_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<CommandState>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetCommandState",
+        client,
+        cursor,
+        |mut client, cursor| async move { get_command_state_with_cursor(&mut client, cursor).await },
+    )
 }
 
 // GetLastCommandResult
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetLastCommandResult",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_last_command_result_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -637,7 +1016,7 @@ async fn get_last_command_result_with_cursor(
     });
     let response = client.get_last_command_result(request).await;
     parse_getter_response(response)
-        .map(|response| (response.metadata.unwrap().cursor, response.into()))
+        .map(|response| (response_cursor(response.metadata, cursor), response.into()))
 }
 
 pub async fn get_last_command_result(
@@ -655,41 +1034,50 @@ pub async fn get_latest_last_command_result(
     get_last_command_result(client, 0).await
 }
 
+pub async fn get_last_command_result_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<Option<CommandResult>>, KachakaApiError> {
+    get_last_command_result_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_last_command_result(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<Option<CommandResult>, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<Option<CommandResult>, KachakaApiError>>();
-
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_last_command_result_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, result)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(result)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
+    watch_last_command_result_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
 
-    UnboundedReceiverStream::new(rx)
+pub async fn watch_last_command_result_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<Option<CommandResult>>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetLastCommandResult",
+        client,
+        cursor,
+        |mut client, cursor| async move {
+            get_last_command_result_with_cursor(&mut client, cursor).await
+        },
+    )
 }
 
 // command api
 // StartCommand
-async fn start_command(
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "StartCommand", skip(client), fields(command_id), err(Debug))
+)]
+pub async fn start_command(
     client: &mut TonicKachakaApiClient<Channel>,
-    command: kachaka_api::command::Command,
+    command: Command,
     options: StartCommandOptions,
 ) -> Result<String, KachakaApiError> {
     let request = tonic::Request::new(kachaka_api::StartCommandRequest {
-        command: Some(kachaka_api::Command {
-            command: Some(command),
-        }),
+        command: Some(command.into()),
         cancel_all: options.cancel_all,
         deferrable: options.deferrable,
         lock_on_end: options.lock_on_end,
@@ -701,7 +1089,11 @@ async fn start_command(
         response,
         |rpc_response: &kachaka_api::StartCommandResponse| rpc_response.result,
     )
-    .map(|response| response.command_id)
+    .map(|response| {
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("command_id", response.command_id.as_str());
+        response.command_id
+    })
 }
 
 pub async fn move_shelf(
@@ -712,10 +1104,10 @@ pub async fn move_shelf(
 ) -> Result<String, KachakaApiError> {
     start_command(
         client,
-        kachaka_api::command::Command::MoveShelfCommand(kachaka_api::MoveShelfCommand {
-            target_shelf_id: shelf_id.to_string(),
-            destination_location_id: location_id.to_string(),
-        }),
+        Command::MoveShelf {
+            shelf_id: shelf_id.to_string(),
+            location_id: location_id.to_string(),
+        },
         options,
     )
     .await
@@ -728,9 +1120,9 @@ pub async fn return_shelf(
 ) -> Result<String, KachakaApiError> {
     start_command(
         client,
-        kachaka_api::command::Command::ReturnShelfCommand(kachaka_api::ReturnShelfCommand {
-            target_shelf_id: shelf_id.to_string(),
-        }),
+        Command::ReturnShelf {
+            shelf_id: shelf_id.to_string(),
+        },
         options,
     )
     .await
@@ -742,9 +1134,9 @@ pub async fn undock_shelf(
 ) -> Result<String, KachakaApiError> {
     start_command(
         client,
-        kachaka_api::command::Command::UndockShelfCommand(kachaka_api::UndockShelfCommand {
-            target_shelf_id: "".to_string(),
-        }),
+        Command::UndockShelf {
+            shelf_id: "".to_string(),
+        },
         options,
     )
     .await
@@ -757,9 +1149,9 @@ pub async fn move_to_location(
 ) -> Result<String, KachakaApiError> {
     start_command(
         client,
-        kachaka_api::command::Command::MoveToLocationCommand(kachaka_api::MoveToLocationCommand {
-            target_location_id: location_id.to_string(),
-        }),
+        Command::MoveToLocation {
+            location_id: location_id.to_string(),
+        },
         options,
     )
     .await
@@ -769,24 +1161,14 @@ pub async fn return_home(
     client: &mut TonicKachakaApiClient<Channel>,
     options: StartCommandOptions,
 ) -> Result<String, KachakaApiError> {
-    start_command(
-        client,
-        kachaka_api::command::Command::ReturnHomeCommand(kachaka_api::ReturnHomeCommand {}),
-        options,
-    )
-    .await
+    start_command(client, Command::ReturnHome, options).await
 }
 
 pub async fn dock_shelf(
     client: &mut TonicKachakaApiClient<Channel>,
     options: StartCommandOptions,
 ) -> Result<String, KachakaApiError> {
-    start_command(
-        client,
-        kachaka_api::command::Command::DockShelfCommand(kachaka_api::DockShelfCommand {}),
-        options,
-    )
-    .await
+    start_command(client, Command::DockShelf, options).await
 }
 
 pub async fn speak(
@@ -796,9 +1178,9 @@ pub async fn speak(
 ) -> Result<String, KachakaApiError> {
     start_command(
         client,
-        kachaka_api::command::Command::SpeakCommand(kachaka_api::SpeakCommand {
+        Command::Speak {
             text: text.to_string(),
-        }),
+        },
         options,
     )
     .await
@@ -806,18 +1188,16 @@ pub async fn speak(
 
 pub async fn move_to_pose(
     client: &mut TonicKachakaApiClient<Channel>,
-    x: f64,
-    y: f64,
-    yaw: f64,
+    pose: &Pose,
     options: StartCommandOptions,
 ) -> Result<String, KachakaApiError> {
     start_command(
         client,
-        kachaka_api::command::Command::MoveToPoseCommand(kachaka_api::MoveToPoseCommand {
-            x,
-            y,
-            yaw,
-        }),
+        Command::MoveToPose {
+            x: pose.x,
+            y: pose.y,
+            yaw: pose.theta,
+        },
         options,
     )
     .await
@@ -828,12 +1208,7 @@ pub async fn lock(
     duration_sec: f64,
     options: StartCommandOptions,
 ) -> Result<String, KachakaApiError> {
-    start_command(
-        client,
-        kachaka_api::command::Command::LockCommand(kachaka_api::LockCommand { duration_sec }),
-        options,
-    )
-    .await
+    start_command(client, Command::Lock { duration_sec }, options).await
 }
 
 pub async fn move_forward(
@@ -844,10 +1219,10 @@ pub async fn move_forward(
 ) -> Result<String, KachakaApiError> {
     start_command(
         client,
-        kachaka_api::command::Command::MoveForwardCommand(kachaka_api::MoveForwardCommand {
+        Command::MoveForward {
             distance_meter,
             speed,
-        }),
+        },
         options,
     )
     .await
@@ -858,14 +1233,7 @@ pub async fn rotate_in_place(
     angle_radian: f64,
     options: StartCommandOptions,
 ) -> Result<String, KachakaApiError> {
-    start_command(
-        client,
-        kachaka_api::command::Command::RotateInPlaceCommand(kachaka_api::RotateInPlaceCommand {
-            angle_radian,
-        }),
-        options,
-    )
-    .await
+    start_command(client, Command::RotateInPlace { angle_radian }, options).await
 }
 
 pub async fn dock_any_shelf_with_registration(
@@ -875,18 +1243,20 @@ pub async fn dock_any_shelf_with_registration(
 ) -> Result<String, KachakaApiError> {
     start_command(
         client,
-        kachaka_api::command::Command::DockAnyShelfWithRegistrationCommand(
-            kachaka_api::DockAnyShelfWithRegistrationCommand {
-                dock_forward: true,
-                target_location_id: location_id.to_string(),
-            },
-        ),
+        Command::DockAnyShelfWithRegistration {
+            location_id: location_id.to_string(),
+            dock_forward: true,
+        },
         options,
     )
     .await
 }
 
 // CancelCommand
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "CancelCommand", skip(client), err(Debug))
+)]
 pub async fn cancel_command(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> Result<(), KachakaApiError> {
@@ -900,6 +1270,10 @@ pub async fn cancel_command(
 }
 
 // Proceed
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "Proceed", skip(client), err(Debug))
+)]
 pub async fn proceed(client: &mut TonicKachakaApiClient<Channel>) -> Result<(), KachakaApiError> {
     let request = tonic::Request::new(kachaka_api::EmptyRequest {});
     let response = client.proceed(request).await;
@@ -909,7 +1283,88 @@ pub async fn proceed(client: &mut TonicKachakaApiClient<Channel>) -> Result<(),
     .map(|_response| ())
 }
 
+// GetPngMap
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetPngMap",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
+async fn get_png_map_with_cursor(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<(i64, kachaka_api::Map), KachakaApiError> {
+    let request = tonic::Request::new(kachaka_api::GetRequest {
+        metadata: Some(kachaka_api::Metadata { cursor }),
+    });
+    let response = client.get_png_map(request).await;
+    let map_result = parse_getter_response(response)?;
+    if let Some(map) = map_result.map {
+        Ok((response_cursor(map_result.metadata, cursor), map))
+    } else {
+        Err(KachakaApiError::NullResult)
+    }
+}
+
+pub async fn get_png_map(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<kachaka_api::Map, KachakaApiError> {
+    get_png_map_with_cursor(client, cursor)
+        .await
+        .map(|(_, map)| map)
+}
+
+pub async fn get_latest_png_map(
+    client: &mut TonicKachakaApiClient<Channel>,
+) -> Result<kachaka_api::Map, KachakaApiError> {
+    get_png_map(client, 0).await
+}
+
+pub async fn get_png_map_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<kachaka_api::Map>, KachakaApiError> {
+    get_png_map_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
+pub async fn watch_png_map(
+    client: &mut TonicKachakaApiClient<Channel>,
+) -> impl Stream<Item = Result<kachaka_api::Map, KachakaApiError>> {
+    watch_png_map_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
+
+pub async fn watch_png_map_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<kachaka_api::Map>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetPngMap",
+        client,
+        cursor,
+        |mut client, cursor| async move { get_png_map_with_cursor(&mut client, cursor).await },
+    )
+}
+
 // GetLocations
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetLocations",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_locations_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -918,8 +1373,12 @@ async fn get_locations_with_cursor(
         metadata: Some(kachaka_api::Metadata { cursor }),
     });
     let response = client.get_locations(request).await;
-    parse_getter_response(response)
-        .map(|response| (response.metadata.unwrap().cursor, response.locations))
+    parse_getter_response(response).map(|response| {
+        (
+            response_cursor(response.metadata, cursor),
+            response.locations,
+        )
+    })
 }
 
 pub async fn get_locations(
@@ -937,30 +1396,46 @@ pub async fn get_latest_locations(
     get_locations(client, 0).await
 }
 
+pub async fn get_locations_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<Vec<kachaka_api::Location>>, KachakaApiError> {
+    get_locations_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_locations(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<Vec<kachaka_api::Location>, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<Vec<kachaka_api::Location>, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-    tokio::spawn(async move {
-        loop {
-            match get_locations_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, locations)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(locations)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
+    watch_locations_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
 
-    UnboundedReceiverStream::new(rx)
+pub async fn watch_locations_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<Vec<kachaka_api::Location>>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetLocations",
+        client,
+        cursor,
+        |mut client, cursor| async move { get_locations_with_cursor(&mut client, cursor).await },
+    )
 }
 
 // GetShelves
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetShelves",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_shelves_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -970,7 +1445,7 @@ async fn get_shelves_with_cursor(
     });
     let response = client.get_shelves(request).await;
     parse_getter_response(response)
-        .map(|response| (response.metadata.unwrap().cursor, response.shelves))
+        .map(|response| (response_cursor(response.metadata, cursor), response.shelves))
 }
 
 pub async fn get_shelves(
@@ -988,31 +1463,46 @@ pub async fn get_latest_shelves(
     get_shelves(client, 0).await
 }
 
+pub async fn get_shelves_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<Vec<kachaka_api::Shelf>>, KachakaApiError> {
+    get_shelves_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_shelves(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<Vec<kachaka_api::Shelf>, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<Vec<kachaka_api::Shelf>, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-
-    tokio::spawn(async move {
-        loop {
-            match get_shelves_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, shelves)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(shelves)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
+    watch_shelves_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
 
-    UnboundedReceiverStream::new(rx)
+pub async fn watch_shelves_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<Vec<kachaka_api::Shelf>>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetShelves",
+        client,
+        cursor,
+        |mut client, cursor| async move { get_shelves_with_cursor(&mut client, cursor).await },
+    )
 }
 
 // GetMovingShelfId
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "GetMovingShelfId",
+        level = "debug",
+        skip(client),
+        fields(new_cursor),
+        err(Debug)
+    )
+)]
 async fn get_moving_shelf_id_with_cursor(
     client: &mut TonicKachakaApiClient<Channel>,
     cursor: i64,
@@ -1021,8 +1511,12 @@ async fn get_moving_shelf_id_with_cursor(
         metadata: Some(kachaka_api::Metadata { cursor }),
     });
     let response = client.get_moving_shelf_id(request).await;
-    parse_getter_response(response)
-        .map(|response| (response.metadata.unwrap().cursor, response.shelf_id))
+    parse_getter_response(response).map(|response| {
+        (
+            response_cursor(response.metadata, cursor),
+            response.shelf_id,
+        )
+    })
 }
 
 pub async fn get_moving_shelf_id(
@@ -1040,31 +1534,40 @@ pub async fn get_latest_moving_shelf_id(
     get_moving_shelf_id(client, 0).await
 }
 
+pub async fn get_moving_shelf_id_versioned(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> Result<Versioned<String>, KachakaApiError> {
+    get_moving_shelf_id_with_cursor(client, cursor)
+        .await
+        .map(|(new_cursor, value)| Versioned::new(value, new_cursor))
+}
+
 pub async fn watch_moving_shelf_id(
     client: &mut TonicKachakaApiClient<Channel>,
 ) -> impl Stream<Item = Result<String, KachakaApiError>> {
-    let (tx, rx) = mpsc::unbounded_channel::<Result<String, KachakaApiError>>();
-    let mut cursor = 0;
-    let mut client_clone = client.clone();
-
-    tokio::spawn(async move {
-        loop {
-            match get_moving_shelf_id_with_cursor(&mut client_clone, cursor).await {
-                Ok((new_cursor, shelf_id)) => {
-                    cursor = new_cursor;
-                    tx.send(Ok(shelf_id)).unwrap();
-                }
-                Err(e) => {
-                    tx.send(Err(e)).unwrap();
-                }
-            }
-        }
-    });
+    watch_moving_shelf_id_from(client, 0)
+        .await
+        .map(|result| result.map(|versioned| versioned.value))
+}
 
-    UnboundedReceiverStream::new(rx)
+pub async fn watch_moving_shelf_id_from(
+    client: &mut TonicKachakaApiClient<Channel>,
+    cursor: i64,
+) -> impl Stream<Item = Result<Versioned<String>, KachakaApiError>> {
+    spawn_versioned_watch(
+        "GetMovingShelfId",
+        client,
+        cursor,
+        |mut client, cursor| async move { get_moving_shelf_id_with_cursor(&mut client, cursor).await },
+    )
 }
 
 // ResetShelfPose
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "ResetShelfPose", skip(client), err(Debug))
+)]
 pub async fn reset_shelf_pose(
     client: &mut TonicKachakaApiClient<Channel>,
     shelf_id: &str,
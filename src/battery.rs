@@ -0,0 +1,389 @@
+use futures::stream::{Stream, StreamExt};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::{BroadcastStream, WatchStream};
+
+use crate::types::{BatteryInfo, Command, CommandState, KachakaApiError, PowerSupplyStatus};
+use crate::{KachakaApiClient, StartCommandOptions};
+
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Estimates charge and discharge rates from battery samples.
+///
+/// The rate of the current status is the slope over the samples of the last
+/// `window`; samples are dropped whenever the power supply status changes, while the
+/// last rate of the other status is kept.
+#[derive(Debug, Clone)]
+pub struct BatteryEstimator {
+    window: Duration,
+    samples: VecDeque<(Instant, f64)>,
+    latest: Option<BatteryInfo>,
+    discharge_rate: Option<f64>,
+    charge_rate: Option<f64>,
+}
+
+impl Default for BatteryEstimator {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(600))
+    }
+}
+
+impl BatteryEstimator {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+            latest: None,
+            discharge_rate: None,
+            charge_rate: None,
+        }
+    }
+
+    pub fn update(&mut self, battery_info: &BatteryInfo, now: Instant) {
+        let status_changed = self
+            .latest
+            .as_ref()
+            .is_some_and(|latest| latest.power_supply_status != battery_info.power_supply_status);
+        if status_changed {
+            self.samples.clear();
+        }
+        self.samples
+            .push_back((now, battery_info.remaining_percentage));
+        while self.samples.len() > 2
+            && now.saturating_duration_since(self.samples[0].0) > self.window
+        {
+            self.samples.pop_front();
+        }
+        self.latest = Some(battery_info.clone());
+
+        let (first, last) = (self.samples[0], self.samples[self.samples.len() - 1]);
+        let hours = last.0.saturating_duration_since(first.0).as_secs_f64() / 3600.0;
+        if hours <= 0.0 {
+            return;
+        }
+        let rate = (last.1 - first.1) / hours;
+        match battery_info.power_supply_status {
+            PowerSupplyStatus::Charging => self.charge_rate = Some(rate.max(0.0)),
+            PowerSupplyStatus::Discharging => self.discharge_rate = Some((-rate).max(0.0)),
+        }
+    }
+
+    pub fn latest(&self) -> Option<&BatteryInfo> {
+        self.latest.as_ref()
+    }
+
+    /// Percent per hour.
+    pub fn discharge_rate(&self) -> Option<f64> {
+        self.discharge_rate
+    }
+
+    /// Percent per hour.
+    pub fn charge_rate(&self) -> Option<f64> {
+        self.charge_rate
+    }
+
+    /// `None` unless discharging at a known, non-zero rate.
+    pub fn time_to_empty(&self) -> Option<Duration> {
+        let latest = self.latest.as_ref()?;
+        if latest.power_supply_status != PowerSupplyStatus::Discharging {
+            return None;
+        }
+        hours_until(latest.remaining_percentage, self.discharge_rate?)
+    }
+
+    /// `None` unless charging at a known, non-zero rate.
+    pub fn time_to_full(&self) -> Option<Duration> {
+        let latest = self.latest.as_ref()?;
+        if latest.power_supply_status != PowerSupplyStatus::Charging {
+            return None;
+        }
+        hours_until(100.0 - latest.remaining_percentage, self.charge_rate?)
+    }
+}
+
+fn hours_until(percentage: f64, rate: f64) -> Option<Duration> {
+    (rate > 0.0).then(|| Duration::from_secs_f64((percentage.max(0.0) / rate) * 3600.0))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryStatus {
+    pub battery_info: BatteryInfo,
+    /// Percent per hour.
+    pub discharge_rate: Option<f64>,
+    /// Percent per hour.
+    pub charge_rate: Option<f64>,
+    pub time_to_empty: Option<Duration>,
+    pub time_to_full: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChargeSession {
+    pub started_at: Instant,
+    /// `None` while charging.
+    pub ended_at: Option<Instant>,
+    pub start_percentage: f64,
+    /// The latest percentage while charging.
+    pub end_percentage: f64,
+}
+
+/// Thresholds in percent of remaining battery.
+#[derive(Debug, Clone)]
+pub struct BatteryPolicy {
+    /// Below this, the robot is sent home once per discharge, as soon as no command
+    /// is running.
+    pub return_home_below: f64,
+    /// Below this, [`BatteryMonitor::start_command`] refuses everything but
+    /// `ReturnHome`.
+    pub critical_below: f64,
+}
+
+impl Default for BatteryPolicy {
+    fn default() -> Self {
+        Self {
+            return_home_below: 20.0,
+            critical_below: 10.0,
+        }
+    }
+}
+
+impl BatteryPolicy {
+    pub fn return_home_below(mut self, percentage: f64) -> Self {
+        self.return_home_below = percentage;
+        self
+    }
+
+    pub fn critical_below(mut self, percentage: f64) -> Self {
+        self.critical_below = percentage;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BatteryEvent {
+    ChargeStarted(ChargeSession),
+    ChargeEnded(ChargeSession),
+    /// The policy sent the robot home.
+    ReturningHome {
+        command_id: String,
+    },
+    ReturnHomeFailed {
+        error: String,
+    },
+}
+
+#[derive(Debug)]
+pub enum BatteryCommandError {
+    /// The battery is below [`BatteryPolicy::critical_below`].
+    Critical(BatteryInfo),
+    KachakaApiError(KachakaApiError),
+}
+
+/// Follows the battery of a robot in a background task, estimating rates and
+/// recording charge sessions, and optionally enforcing a [`BatteryPolicy`].
+///
+/// The task stops when the monitor is dropped.
+pub struct BatteryMonitor {
+    client: KachakaApiClient,
+    policy: Option<BatteryPolicy>,
+    status: watch::Receiver<Option<BatteryStatus>>,
+    sessions: Arc<Mutex<Vec<ChargeSession>>>,
+    events: broadcast::Sender<BatteryEvent>,
+    task: JoinHandle<()>,
+}
+
+impl BatteryMonitor {
+    pub fn spawn(client: KachakaApiClient, policy: Option<BatteryPolicy>) -> Self {
+        let (status_tx, status) = watch::channel(None);
+        let sessions = Arc::new(Mutex::new(Vec::new()));
+        let (events, _) = broadcast::channel(64);
+        let runner = MonitorRunner {
+            client: client.clone(),
+            policy: policy.clone(),
+            estimator: BatteryEstimator::default(),
+            status: status_tx,
+            sessions: sessions.clone(),
+            events: events.clone(),
+            command_state: None,
+            returning_home: false,
+        };
+        Self {
+            client,
+            policy,
+            status,
+            sessions,
+            events,
+            task: tokio::spawn(runner.run()),
+        }
+    }
+
+    /// `None` until the robot has reported its battery.
+    pub fn status(&self) -> Option<BatteryStatus> {
+        self.status.borrow().clone()
+    }
+
+    pub fn watch_status(&self) -> impl Stream<Item = BatteryStatus> {
+        WatchStream::new(self.status.clone()).filter_map(futures::future::ready)
+    }
+
+    /// Charge sessions since the monitor started, the last one possibly ongoing.
+    pub fn charge_sessions(&self) -> Vec<ChargeSession> {
+        self.sessions.lock().unwrap().clone()
+    }
+
+    pub fn events(&self) -> impl Stream<Item = BatteryEvent> {
+        BroadcastStream::new(self.events.subscribe())
+            .filter_map(|event| futures::future::ready(event.ok()))
+    }
+
+    pub fn is_critical(&self) -> bool {
+        match (&self.policy, self.status.borrow().as_ref()) {
+            (Some(policy), Some(status)) => {
+                status.battery_info.remaining_percentage < policy.critical_below
+            }
+            _ => false,
+        }
+    }
+
+    /// Starts a command unless the battery is critical; `ReturnHome` is always allowed.
+    pub async fn start_command(
+        &self,
+        command: Command,
+        options: StartCommandOptions,
+    ) -> Result<String, BatteryCommandError> {
+        if command != Command::ReturnHome && self.is_critical() {
+            let status = self.status().unwrap();
+            return Err(BatteryCommandError::Critical(status.battery_info));
+        }
+        self.client
+            .clone()
+            .start_command(command, options)
+            .await
+            .map_err(BatteryCommandError::KachakaApiError)
+    }
+}
+
+impl Drop for BatteryMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct MonitorRunner {
+    client: KachakaApiClient,
+    policy: Option<BatteryPolicy>,
+    estimator: BatteryEstimator,
+    status: watch::Sender<Option<BatteryStatus>>,
+    sessions: Arc<Mutex<Vec<ChargeSession>>>,
+    events: broadcast::Sender<BatteryEvent>,
+    command_state: Option<CommandState>,
+    returning_home: bool,
+}
+
+impl MonitorRunner {
+    async fn run(mut self) {
+        let mut client = self.client.clone();
+        loop {
+            let mut battery_stream = Box::pin(client.watch_battery_info().await);
+            let mut command_state_stream = Box::pin(client.watch_command_state().await);
+            loop {
+                tokio::select! {
+                    item = battery_stream.next() => match item {
+                        Some(Ok(battery_info)) => self.on_battery_info(battery_info),
+                        _ => break,
+                    },
+                    item = command_state_stream.next() => match item {
+                        Some(Ok(command_state)) => self.command_state = Some(command_state),
+                        _ => break,
+                    },
+                }
+                self.apply_policy().await;
+            }
+            tokio::time::sleep(RESUBSCRIBE_INTERVAL).await;
+        }
+    }
+
+    fn on_battery_info(&mut self, battery_info: BatteryInfo) {
+        let now = Instant::now();
+        let previous_status = self
+            .estimator
+            .latest()
+            .map(|latest| latest.power_supply_status.clone());
+        self.estimator.update(&battery_info, now);
+        let charging = battery_info.power_supply_status == PowerSupplyStatus::Charging;
+        let percentage = battery_info.remaining_percentage;
+
+        let mut sessions = self.sessions.lock().unwrap();
+        match (previous_status, charging) {
+            (Some(PowerSupplyStatus::Charging), true) => {
+                if let Some(session) = sessions.last_mut() {
+                    session.end_percentage = percentage;
+                }
+            }
+            (Some(PowerSupplyStatus::Charging), false) => {
+                if let Some(session) = sessions.last_mut() {
+                    session.ended_at = Some(now);
+                    session.end_percentage = percentage;
+                    let _ = self.events.send(BatteryEvent::ChargeEnded(session.clone()));
+                }
+            }
+            (_, true) => {
+                let session = ChargeSession {
+                    started_at: now,
+                    ended_at: None,
+                    start_percentage: percentage,
+                    end_percentage: percentage,
+                };
+                let _ = self
+                    .events
+                    .send(BatteryEvent::ChargeStarted(session.clone()));
+                sessions.push(session);
+                self.returning_home = false;
+            }
+            (_, false) => {}
+        }
+        drop(sessions);
+
+        self.status.send_replace(Some(BatteryStatus {
+            battery_info,
+            discharge_rate: self.estimator.discharge_rate(),
+            charge_rate: self.estimator.charge_rate(),
+            time_to_empty: self.estimator.time_to_empty(),
+            time_to_full: self.estimator.time_to_full(),
+        }));
+    }
+
+    async fn apply_policy(&mut self) {
+        let Some(policy) = &self.policy else {
+            return;
+        };
+        let Some(latest) = self.estimator.latest() else {
+            return;
+        };
+        if self.returning_home
+            || latest.power_supply_status != PowerSupplyStatus::Discharging
+            || latest.remaining_percentage >= policy.return_home_below
+            || self.command_state != Some(CommandState::Unspecified)
+        {
+            return;
+        }
+        self.returning_home = true;
+        let event = match self
+            .client
+            .return_home(StartCommandOptions::default())
+            .await
+        {
+            Ok(command_id) => BatteryEvent::ReturningHome { command_id },
+            Err(error) => {
+                // try again on the next update
+                self.returning_home = false;
+                BatteryEvent::ReturnHomeFailed {
+                    error: format!("{:?}", error),
+                }
+            }
+        };
+        let _ = self.events.send(event);
+    }
+}
@@ -0,0 +1,543 @@
+#![allow(clippy::result_large_err)]
+
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::stream::{BoxStream, Stream, StreamExt};
+use kachaka_api::kachaka_api::{Location, Shelf};
+use kachaka_api::mjpeg::{mjpeg_response, CameraBroadcaster};
+use kachaka_api::types::PowerSupplyStatus;
+use kachaka_api::{
+    BatteryInfo, Camera, Command, CommandResult, CommandState, KachakaApiClient, KachakaApiError,
+    KachakaError, Pose, StartCommandOptions,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::io::Cursor;
+use std::sync::Arc;
+use tonic::transport::Endpoint;
+use utoipa::{OpenApi, ToSchema};
+
+/// Gateway configuration, read from the TOML file given as the only argument.
+///
+/// ```toml
+/// listen = "0.0.0.0:8080"
+///
+/// [[robots]]
+/// name = "kachaka-1"
+/// url = "http://kachaka-020.local:26400"
+/// ```
+#[derive(Deserialize)]
+struct GatewayConfig {
+    #[serde(default = "default_listen")]
+    listen: String,
+    robots: Vec<RobotConfig>,
+}
+
+#[derive(Deserialize)]
+struct RobotConfig {
+    name: String,
+    url: String,
+}
+
+fn default_listen() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+struct AppState {
+    robots: BTreeMap<String, KachakaApiClient>,
+    camera_streams: HashMap<(String, Camera), CameraBroadcaster>,
+}
+
+type SharedState = Arc<AppState>;
+
+enum GatewayError {
+    RobotNotFound(String),
+    Api(KachakaApiError),
+    Image(image::ImageError),
+}
+
+impl From<KachakaApiError> for GatewayError {
+    fn from(error: KachakaApiError) -> Self {
+        GatewayError::Api(error)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorBody {
+    error: String,
+    /// Set when the robot reported an error.
+    error_code: Option<i32>,
+}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let (status, error, error_code) = match self {
+            GatewayError::RobotNotFound(robot) => (
+                StatusCode::NOT_FOUND,
+                format!("robot not found: {}", robot),
+                None,
+            ),
+            GatewayError::Api(KachakaApiError::ApiError(error)) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "robot error".to_string(),
+                Some(error.error_code),
+            ),
+            GatewayError::Api(KachakaApiError::CommunicationError(status)) => (
+                StatusCode::BAD_GATEWAY,
+                format!("communication error: {}", status.message()),
+                None,
+            ),
+            GatewayError::Api(KachakaApiError::NullResult) => {
+                (StatusCode::BAD_GATEWAY, "empty result".to_string(), None)
+            }
+            GatewayError::Api(KachakaApiError::JsonParseError(error)) => (
+                StatusCode::BAD_GATEWAY,
+                format!("invalid JSON: {}", error),
+                None,
+            ),
+            GatewayError::Api(KachakaApiError::ImageDecodeError(error)) => (
+                StatusCode::BAD_GATEWAY,
+                format!("invalid image: {}", error),
+                None,
+            ),
+            GatewayError::Image(error) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("image encoding failed: {}", error),
+                None,
+            ),
+        };
+        (status, Json(ErrorBody { error, error_code })).into_response()
+    }
+}
+
+fn robot(state: &AppState, robot: &str) -> Result<KachakaApiClient, GatewayError> {
+    state
+        .robots
+        .get(robot)
+        .cloned()
+        .ok_or_else(|| GatewayError::RobotNotFound(robot.to_string()))
+}
+
+#[derive(Serialize, ToSchema)]
+struct RobotInfo {
+    serial_number: String,
+    version: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct StartCommandRequest {
+    command: Command,
+    #[serde(default)]
+    options: StartCommandOptions,
+}
+
+#[derive(Serialize, ToSchema)]
+struct StartCommandResponse {
+    command_id: String,
+}
+
+#[derive(Deserialize, Clone, Copy, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum WatchTopic {
+    Pose,
+    Battery,
+    Errors,
+    CommandState,
+    LastCommandResult,
+    MovingShelfId,
+    Locations,
+    Shelves,
+}
+
+#[utoipa::path(get, path = "/robots", responses((status = 200, body = Vec<String>)))]
+async fn list_robots(State(state): State<SharedState>) -> Json<Vec<String>> {
+    Json(state.robots.keys().cloned().collect())
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/info",
+    params(("robot" = String, Path)),
+    responses((status = 200, body = RobotInfo), (status = "default", body = ErrorBody))
+)]
+async fn get_info(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<RobotInfo>, GatewayError> {
+    let mut client = robot(&state, &name)?;
+    Ok(Json(RobotInfo {
+        serial_number: client.get_latest_robot_serial_number().await?,
+        version: client.get_latest_robot_version().await?,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/pose",
+    params(("robot" = String, Path)),
+    responses((status = 200, body = Pose), (status = "default", body = ErrorBody))
+)]
+async fn get_pose(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<Pose>, GatewayError> {
+    Ok(Json(robot(&state, &name)?.get_latest_robot_pose().await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/battery",
+    params(("robot" = String, Path)),
+    responses((status = 200, body = BatteryInfo), (status = "default", body = ErrorBody))
+)]
+async fn get_battery(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<BatteryInfo>, GatewayError> {
+    Ok(Json(robot(&state, &name)?.get_latest_battery_info().await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/errors",
+    params(("robot" = String, Path)),
+    responses((status = 200, body = Vec<KachakaError>), (status = "default", body = ErrorBody))
+)]
+async fn get_errors(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<KachakaError>>, GatewayError> {
+    Ok(Json(robot(&state, &name)?.get_latest_error().await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/command_state",
+    params(("robot" = String, Path)),
+    responses((status = 200, body = CommandState), (status = "default", body = ErrorBody))
+)]
+async fn get_command_state(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<CommandState>, GatewayError> {
+    Ok(Json(
+        robot(&state, &name)?.get_latest_command_state().await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/last_command_result",
+    params(("robot" = String, Path)),
+    responses(
+        (status = 200, body = Option<CommandResult>),
+        (status = "default", body = ErrorBody)
+    )
+)]
+async fn get_last_command_result(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<Option<CommandResult>>, GatewayError> {
+    Ok(Json(
+        robot(&state, &name)?.get_last_command_result(0).await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/locations",
+    params(("robot" = String, Path)),
+    responses(
+        (status = 200, body = Vec<Location>),
+        (status = "default", body = ErrorBody)
+    )
+)]
+async fn get_locations(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<Location>>, GatewayError> {
+    Ok(Json(robot(&state, &name)?.get_latest_locations().await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/shelves",
+    params(("robot" = String, Path)),
+    responses(
+        (status = 200, body = Vec<Shelf>),
+        (status = "default", body = ErrorBody)
+    )
+)]
+async fn get_shelves(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<Shelf>>, GatewayError> {
+    Ok(Json(robot(&state, &name)?.get_latest_shelves().await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/moving_shelf_id",
+    params(("robot" = String, Path)),
+    responses((status = 200, body = String), (status = "default", body = ErrorBody))
+)]
+async fn get_moving_shelf_id(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<String>, GatewayError> {
+    Ok(Json(
+        robot(&state, &name)?.get_latest_moving_shelf_id().await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/cameras/{camera}/latest.jpg",
+    params(("robot" = String, Path), ("camera" = Camera, Path)),
+    responses(
+        (status = 200, content_type = "image/jpeg", body = Vec<u8>),
+        (status = "default", body = ErrorBody)
+    )
+)]
+async fn get_camera_image(
+    State(state): State<SharedState>,
+    Path((name, camera)): Path<(String, Camera)>,
+) -> Result<Response, GatewayError> {
+    let mut client = robot(&state, &name)?;
+    let image = match camera {
+        Camera::Front => {
+            client
+                .get_latest_front_camera_ros_compressed_image()
+                .await?
+        }
+        Camera::Back => client.get_latest_back_camera_ros_compressed_image().await?,
+    };
+    let mut jpeg = Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_to(&mut jpeg, image::ImageFormat::Jpeg)
+        .map_err(GatewayError::Image)?;
+    Ok(([(header::CONTENT_TYPE, "image/jpeg")], jpeg.into_inner()).into_response())
+}
+
+/// All viewers of a camera share one subscription to the robot.
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/cameras/{camera}/stream.mjpeg",
+    params(("robot" = String, Path), ("camera" = Camera, Path)),
+    responses(
+        (status = 200, content_type = "multipart/x-mixed-replace", body = Vec<u8>),
+        (status = "default", body = ErrorBody)
+    )
+)]
+async fn stream_camera(
+    State(state): State<SharedState>,
+    Path((name, camera)): Path<(String, Camera)>,
+) -> Result<Response, GatewayError> {
+    let broadcaster = state
+        .camera_streams
+        .get(&(name.clone(), camera))
+        .ok_or(GatewayError::RobotNotFound(name))?;
+    Ok(mjpeg_response(broadcaster.subscribe()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/robots/{robot}/commands",
+    params(("robot" = String, Path)),
+    request_body = StartCommandRequest,
+    responses(
+        (status = 200, body = StartCommandResponse),
+        (status = "default", body = ErrorBody)
+    )
+)]
+async fn start_command(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(request): Json<StartCommandRequest>,
+) -> Result<Json<StartCommandResponse>, GatewayError> {
+    let command_id = robot(&state, &name)?
+        .start_command(request.command, request.options)
+        .await?;
+    Ok(Json(StartCommandResponse { command_id }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/robots/{robot}/cancel",
+    params(("robot" = String, Path)),
+    responses((status = 204), (status = "default", body = ErrorBody))
+)]
+async fn cancel_command(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, GatewayError> {
+    robot(&state, &name)?.cancel_command().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/robots/{robot}/proceed",
+    params(("robot" = String, Path)),
+    responses((status = 204), (status = "default", body = ErrorBody))
+)]
+async fn proceed(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, GatewayError> {
+    robot(&state, &name)?.proceed().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Server-sent events; every event carries the same JSON as the matching GET endpoint.
+#[utoipa::path(
+    get,
+    path = "/robots/{robot}/watch/{topic}",
+    params(("robot" = String, Path), ("topic" = WatchTopic, Path)),
+    responses(
+        (status = 200, content_type = "text/event-stream", body = String),
+        (status = "default", body = ErrorBody)
+    )
+)]
+async fn watch(
+    State(state): State<SharedState>,
+    Path((name, topic)): Path<(String, WatchTopic)>,
+) -> Result<Sse<BoxStream<'static, Result<Event, Infallible>>>, GatewayError> {
+    let mut client = robot(&state, &name)?;
+    let events = match topic {
+        WatchTopic::Pose => to_events(client.watch_robot_pose().await),
+        WatchTopic::Battery => to_events(client.watch_battery_info().await),
+        WatchTopic::Errors => to_events(client.watch_error().await),
+        WatchTopic::CommandState => to_events(client.watch_command_state().await),
+        WatchTopic::LastCommandResult => to_events(client.watch_last_command_result().await),
+        WatchTopic::MovingShelfId => to_events(client.watch_moving_shelf_id().await),
+        WatchTopic::Locations => to_events(client.watch_locations().await),
+        WatchTopic::Shelves => to_events(client.watch_shelves().await),
+    };
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Failed polls are sent as `error` events; the stream itself keeps running.
+fn to_events<T: Serialize>(
+    stream: impl Stream<Item = Result<T, KachakaApiError>> + Send + 'static,
+) -> BoxStream<'static, Result<Event, Infallible>> {
+    stream
+        .map(|item| {
+            let event = match item {
+                Ok(value) => Event::default().json_data(value),
+                Err(error) => Event::default()
+                    .event("error")
+                    .json_data(format!("{:?}", error)),
+            };
+            Ok(event.unwrap_or_else(|_| Event::default().event("error")))
+        })
+        .boxed()
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Kachaka REST gateway"),
+    paths(
+        list_robots,
+        get_info,
+        get_pose,
+        get_battery,
+        get_errors,
+        get_command_state,
+        get_last_command_result,
+        get_locations,
+        get_shelves,
+        get_moving_shelf_id,
+        get_camera_image,
+        stream_camera,
+        start_command,
+        cancel_command,
+        proceed,
+        watch,
+    ),
+    components(schemas(PowerSupplyStatus, Camera))
+)]
+struct ApiDoc;
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[tokio::main]
+async fn main() {
+    let config_path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "kachaka-gateway.toml".to_string());
+    let config: GatewayConfig = toml::from_str(
+        &std::fs::read_to_string(&config_path)
+            .unwrap_or_else(|error| panic!("failed to read {}: {}", config_path, error)),
+    )
+    .unwrap_or_else(|error| panic!("invalid config {}: {}", config_path, error));
+
+    // Channels connect lazily, so the gateway starts even while a robot is offline.
+    let robots: BTreeMap<String, KachakaApiClient> = config
+        .robots
+        .into_iter()
+        .map(|robot| {
+            let channel = Endpoint::from_shared(robot.url)
+                .unwrap_or_else(|error| panic!("invalid url for {}: {}", robot.name, error))
+                .connect_lazy();
+            (robot.name, KachakaApiClient::new(channel))
+        })
+        .collect();
+    let camera_streams = robots
+        .iter()
+        .flat_map(|(name, client)| {
+            [Camera::Front, Camera::Back].map(|camera| {
+                (
+                    (name.clone(), camera),
+                    CameraBroadcaster::new(client.clone(), camera),
+                )
+            })
+        })
+        .collect();
+    let state = Arc::new(AppState {
+        robots,
+        camera_streams,
+    });
+
+    let app = Router::new()
+        .route("/openapi.json", get(openapi))
+        .route("/robots", get(list_robots))
+        .route("/robots/:robot/info", get(get_info))
+        .route("/robots/:robot/pose", get(get_pose))
+        .route("/robots/:robot/battery", get(get_battery))
+        .route("/robots/:robot/errors", get(get_errors))
+        .route("/robots/:robot/command_state", get(get_command_state))
+        .route(
+            "/robots/:robot/last_command_result",
+            get(get_last_command_result),
+        )
+        .route("/robots/:robot/locations", get(get_locations))
+        .route("/robots/:robot/shelves", get(get_shelves))
+        .route("/robots/:robot/moving_shelf_id", get(get_moving_shelf_id))
+        .route(
+            "/robots/:robot/cameras/:camera/latest.jpg",
+            get(get_camera_image),
+        )
+        .route(
+            "/robots/:robot/cameras/:camera/stream.mjpeg",
+            get(stream_camera),
+        )
+        .route("/robots/:robot/commands", post(start_command))
+        .route("/robots/:robot/cancel", post(cancel_command))
+        .route("/robots/:robot/proceed", post(proceed))
+        .route("/robots/:robot/watch/:topic", get(watch))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&config.listen)
+        .await
+        .unwrap_or_else(|error| panic!("failed to listen on {}: {}", config.listen, error));
+    println!("listening on {}", config.listen);
+    axum::serve(listener, app).await.unwrap();
+}
@@ -0,0 +1,351 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::stream::StreamExt;
+use kachaka_api::mission::{Mission, MissionEvent, MissionStep};
+use kachaka_api::shelf_location_resolver::{LayoutLookupError, ShelfLocationResolver};
+use kachaka_api::{Command, KachakaApiClient, KachakaApiError, KachakaError, StartCommandOptions};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const LAYOUT_TIMEOUT: Duration = Duration::from_secs(5);
+
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  success
+  1  other failure (e.g. writing the output file)
+  2  invalid arguments
+  3  the robot reported an error (KachakaApiError::ApiError)
+  4  communication failure (KachakaApiError::CommunicationError)
+  5  the robot returned an empty result (KachakaApiError::NullResult)
+  6  invalid JSON from the robot (KachakaApiError::JsonParseError)
+  7  shelf or location not found or ambiguous
+  8  undecodable image from the robot (KachakaApiError::ImageDecodeError)";
+
+#[derive(Parser)]
+#[command(name = "kachaka", about = "Command-line client for the Kachaka API", after_help = EXIT_CODES_HELP)]
+struct Cli {
+    /// gRPC endpoint of the robot.
+    #[arg(
+        long,
+        env = "KACHAKA_URL",
+        default_value = "http://kachaka.local:26400"
+    )]
+    url: String,
+    /// Print results as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Show the serial number and software version.
+    Info,
+    /// Show the current pose on the map.
+    Pose,
+    /// Show the battery state.
+    Battery,
+    /// Show the current errors.
+    Errors {
+        /// Keep printing errors as they change.
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Move a shelf to a location; both accept a name or an id.
+    MoveShelf {
+        shelf: String,
+        location: String,
+        /// Wait until the command finishes.
+        #[arg(long)]
+        wait: bool,
+    },
+    /// Speak a text.
+    Speak {
+        text: String,
+        /// Wait until the command finishes.
+        #[arg(long)]
+        wait: bool,
+    },
+    /// Cancel the running command.
+    Cancel,
+    /// Resume a command waiting for user input.
+    Proceed,
+    /// List the registered locations.
+    Locations,
+    /// List the registered shelves.
+    Shelves,
+    /// Save the latest camera image; the format follows the file extension.
+    SnapshotCamera {
+        camera: Camera,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Camera {
+    Front,
+    Back,
+}
+
+enum CliError {
+    Api(KachakaApiError),
+    Connect(tonic::transport::Error),
+    ShelfLookup(LayoutLookupError),
+    LocationLookup(LayoutLookupError),
+    Image(image::ImageError),
+}
+
+impl From<KachakaApiError> for CliError {
+    fn from(error: KachakaApiError) -> Self {
+        CliError::Api(error)
+    }
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Api(KachakaApiError::ApiError(_)) => 3,
+            CliError::Api(KachakaApiError::CommunicationError(_)) | CliError::Connect(_) => 4,
+            CliError::Api(KachakaApiError::NullResult) => 5,
+            CliError::Api(KachakaApiError::JsonParseError(_)) => 6,
+            CliError::ShelfLookup(_) | CliError::LocationLookup(_) => 7,
+            CliError::Api(KachakaApiError::ImageDecodeError(_)) => 8,
+            CliError::Image(_) => 1,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            CliError::Api(KachakaApiError::ApiError(error)) => {
+                format!("robot error: {}", error.error_code)
+            }
+            CliError::Api(KachakaApiError::CommunicationError(status)) => {
+                format!("communication error: {}", status.message())
+            }
+            CliError::Api(KachakaApiError::NullResult) => "empty result".to_string(),
+            CliError::Api(KachakaApiError::JsonParseError(error)) => {
+                format!("invalid JSON: {}", error)
+            }
+            CliError::Api(KachakaApiError::ImageDecodeError(error)) => {
+                format!("invalid image: {}", error)
+            }
+            CliError::Connect(error) => format!("connection failed: {}", error),
+            CliError::ShelfLookup(error) => lookup_error_message("shelf", error),
+            CliError::LocationLookup(error) => lookup_error_message("location", error),
+            CliError::Image(error) => format!("failed to save image: {}", error),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("kachaka: {}", error.message());
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), CliError> {
+    let mut client = KachakaApiClient::connect(cli.url)
+        .await
+        .map_err(CliError::Connect)?;
+    let json = cli.json;
+    match cli.command {
+        CliCommand::Info => {
+            #[derive(Serialize)]
+            struct Info {
+                serial_number: String,
+                version: String,
+            }
+            let info = Info {
+                serial_number: client.get_latest_robot_serial_number().await?,
+                version: client.get_latest_robot_version().await?,
+            };
+            print(json, &info, || {
+                format!(
+                    "serial number: {}\nversion: {}",
+                    info.serial_number, info.version
+                )
+            });
+        }
+        CliCommand::Pose => {
+            let pose = client.get_latest_robot_pose().await?;
+            print(json, &pose, || {
+                format!(
+                    "x: {:.3}\ny: {:.3}\ntheta: {:.3}",
+                    pose.x, pose.y, pose.theta
+                )
+            });
+        }
+        CliCommand::Battery => {
+            let battery_info = client.get_latest_battery_info().await?;
+            print(json, &battery_info, || {
+                format!(
+                    "remaining: {:.0}%\nstatus: {:?}",
+                    battery_info.remaining_percentage, battery_info.power_supply_status
+                )
+            });
+        }
+        CliCommand::Errors { watch: false } => {
+            let errors = client.get_latest_error().await?;
+            print_errors(json, &errors);
+        }
+        CliCommand::Errors { watch: true } => {
+            let mut error_stream = Box::pin(client.watch_error().await);
+            while let Some(errors) = error_stream.next().await {
+                print_errors(json, &errors?);
+            }
+        }
+        CliCommand::MoveShelf {
+            shelf,
+            location,
+            wait,
+        } => {
+            let (resolver, _) = ShelfLocationResolver::spawn(client.clone());
+            if tokio::time::timeout(LAYOUT_TIMEOUT, resolver.wait_until_ready())
+                .await
+                .is_err()
+            {
+                return Err(CliError::Api(KachakaApiError::CommunicationError(
+                    tonic::Status::deadline_exceeded("layout not received"),
+                )));
+            }
+            let shelf_id = resolver
+                .resolve_shelf(&shelf)
+                .await
+                .map_err(CliError::ShelfLookup)?
+                .id;
+            let location_id = resolver
+                .resolve_location(&location)
+                .await
+                .map_err(CliError::LocationLookup)?
+                .id;
+            resolver.stop();
+            let command = Command::MoveShelf {
+                shelf_id,
+                location_id,
+            };
+            run_command(client, json, command, wait).await?;
+        }
+        CliCommand::Speak { text, wait } => {
+            run_command(client, json, Command::Speak { text }, wait).await?;
+        }
+        CliCommand::Cancel => client.cancel_command().await?,
+        CliCommand::Proceed => client.proceed().await?,
+        CliCommand::Locations => {
+            let locations = client.get_latest_locations().await?;
+            print(json, &locations, || {
+                locations
+                    .iter()
+                    .map(|location| format!("{}\t{}", location.id, location.name))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        CliCommand::Shelves => {
+            let shelves = client.get_latest_shelves().await?;
+            print(json, &shelves, || {
+                shelves
+                    .iter()
+                    .map(|shelf| format!("{}\t{}", shelf.id, shelf.name))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        CliCommand::SnapshotCamera { camera, output } => {
+            let image = match camera {
+                Camera::Front => {
+                    client
+                        .get_latest_front_camera_ros_compressed_image()
+                        .await?
+                }
+                Camera::Back => client.get_latest_back_camera_ros_compressed_image().await?,
+            };
+            image.save(&output).map_err(CliError::Image)?;
+        }
+    }
+    Ok(())
+}
+
+fn print<T: Serialize>(json: bool, value: &T, text: impl FnOnce() -> String) {
+    if json {
+        println!("{}", serde_json::to_string(value).unwrap());
+    } else {
+        println!("{}", text());
+    }
+}
+
+fn print_errors(json: bool, errors: &[KachakaError]) {
+    print(json, &errors, || {
+        if errors.is_empty() {
+            "no errors".to_string()
+        } else {
+            errors
+                .iter()
+                .map(|error| error.error_code.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    });
+}
+
+fn lookup_error_message(kind: &str, error: &LayoutLookupError) -> String {
+    match error {
+        LayoutLookupError::NotFound { name, suggestions } if suggestions.is_empty() => {
+            format!("{} not found: {}", kind, name)
+        }
+        LayoutLookupError::NotFound { name, suggestions } => format!(
+            "{} not found: {} (did you mean: {}?)",
+            kind,
+            name,
+            suggestions.join(", ")
+        ),
+        LayoutLookupError::Ambiguous { name, ids } => {
+            format!("{} is ambiguous: {} (ids: {})", kind, name, ids.join(", "))
+        }
+    }
+}
+
+async fn run_command(
+    mut client: KachakaApiClient,
+    json: bool,
+    command: Command,
+    wait: bool,
+) -> Result<(), CliError> {
+    #[derive(Serialize)]
+    struct Started {
+        command_id: String,
+    }
+    if !wait {
+        let command_id = client
+            .start_command(command, StartCommandOptions::default())
+            .await?;
+        let started = Started { command_id };
+        print(json, &started, || started.command_id.clone());
+        return Ok(());
+    }
+    let mut events = Box::pin(
+        Mission::new()
+            .step(MissionStep::new(command))
+            .run(client)
+            .await,
+    );
+    while let Some(event) = events.next().await {
+        match event {
+            MissionEvent::StepStarted { command_id, .. } => {
+                let started = Started { command_id };
+                print(json, &started, || started.command_id.clone());
+            }
+            MissionEvent::StepFailed { error, .. } => return Err(error.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use image::DynamicImage;
+
+use crate::kachaka_api;
+use crate::types::{Camera, KachakaApiError};
+
+/// Where the compressed image of a [`CameraFrame`] gets decoded, if at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameDecoding {
+    /// Keep the compressed bytes, e.g. to store or forward them.
+    #[default]
+    None,
+    /// Decode on the task that receives the frame.
+    Inline,
+    /// Decode on tokio's blocking thread pool, so that large JPEGs do not stall the
+    /// async runtime.
+    Blocking,
+}
+
+#[derive(Debug, Clone)]
+pub enum FrameData {
+    Compressed(Vec<u8>),
+    Decoded(DynamicImage),
+}
+
+/// A camera image together with its ROS header.
+#[derive(Debug, Clone)]
+pub struct CameraFrame {
+    pub camera: Camera,
+    /// ROS header time; 0 when the robot did not set it.
+    pub stamp_nsec: i64,
+    pub frame_id: String,
+    /// `format` of a compressed image (e.g. `jpeg`) or `encoding` of a raw one.
+    pub encoding: String,
+    pub data: FrameData,
+}
+
+impl CameraFrame {
+    pub fn from_compressed(camera: Camera, image: kachaka_api::RosCompressedImage) -> Self {
+        let header = image.header.unwrap_or_default();
+        Self {
+            camera,
+            stamp_nsec: header.stamp_nsec,
+            frame_id: header.frame_id,
+            encoding: image.format,
+            data: FrameData::Compressed(image.data),
+        }
+    }
+
+    /// Raw images are always converted, as this does not involve decoding.
+    pub fn from_raw(camera: Camera, mut image: kachaka_api::RosImage) -> Self {
+        let header = image.header.take().unwrap_or_default();
+        let encoding = image.encoding.clone();
+        Self {
+            camera,
+            stamp_nsec: header.stamp_nsec,
+            frame_id: header.frame_id,
+            encoding,
+            data: FrameData::Decoded(DynamicImage::from(image)),
+        }
+    }
+
+    pub fn is_decoded(&self) -> bool {
+        matches!(self.data, FrameData::Decoded(_))
+    }
+
+    pub fn image(&self) -> Option<&DynamicImage> {
+        match &self.data {
+            FrameData::Decoded(image) => Some(image),
+            FrameData::Compressed(_) => None,
+        }
+    }
+
+    pub fn compressed_data(&self) -> Option<&[u8]> {
+        match &self.data {
+            FrameData::Compressed(data) => Some(data),
+            FrameData::Decoded(_) => None,
+        }
+    }
+
+    /// Decodes the compressed image on the current thread; decoded frames are
+    /// returned as they are.
+    pub fn decode(self) -> Result<Self, KachakaApiError> {
+        let FrameData::Compressed(data) = &self.data else {
+            return Ok(self);
+        };
+        let image = image::load_from_memory(data).map_err(KachakaApiError::ImageDecodeError)?;
+        Ok(Self {
+            data: FrameData::Decoded(image),
+            ..self
+        })
+    }
+
+    /// Like [`CameraFrame::decode`], on tokio's blocking thread pool.
+    pub async fn decode_blocking(self) -> Result<Self, KachakaApiError> {
+        if self.is_decoded() {
+            return Ok(self);
+        }
+        match tokio::task::spawn_blocking(move || self.decode()).await {
+            Ok(result) => result,
+            Err(error) => std::panic::resume_unwind(error.into_panic()),
+        }
+    }
+
+    pub async fn decode_with(self, decoding: FrameDecoding) -> Result<Self, KachakaApiError> {
+        match decoding {
+            FrameDecoding::None => Ok(self),
+            FrameDecoding::Inline => self.decode(),
+            FrameDecoding::Blocking => self.decode_blocking().await,
+        }
+    }
+
+    /// The decoded image, decoding it on the current thread if needed.
+    pub fn into_image(self) -> Result<DynamicImage, KachakaApiError> {
+        match self.decode()?.data {
+            FrameData::Decoded(image) => Ok(image),
+            FrameData::Compressed(_) => unreachable!(),
+        }
+    }
+}
@@ -1,6 +1,23 @@
 use crate::kachaka_api;
-use crate::types::{CommandResult, CommandState, KachakaError, Pose, PowerSupplyStatus};
+use crate::types::{
+    Command, CommandResult, CommandState, KachakaApiError, KachakaError, Pose, PowerSupplyStatus,
+};
 use image::DynamicImage;
+impl From<std::result::Result<(), KachakaError>> for kachaka_api::Result {
+    fn from(result: std::result::Result<(), KachakaError>) -> Self {
+        match result {
+            Ok(()) => kachaka_api::Result {
+                success: true,
+                error_code: 0,
+            },
+            Err(error) => kachaka_api::Result {
+                success: false,
+                error_code: error.error_code,
+            },
+        }
+    }
+}
+
 impl From<kachaka_api::Result> for std::result::Result<(), KachakaError> {
     fn from(result: kachaka_api::Result) -> Self {
         if result.success {
@@ -23,6 +40,51 @@ impl From<kachaka_api::Pose> for Pose {
     }
 }
 
+impl From<&kachaka_api::Pose> for Pose {
+    fn from(pose: &kachaka_api::Pose) -> Self {
+        Pose {
+            x: pose.x,
+            y: pose.y,
+            theta: pose.theta,
+        }
+    }
+}
+
+// `NullResult` when the location or shelf has no pose
+impl TryFrom<&kachaka_api::Location> for Pose {
+    type Error = KachakaApiError;
+
+    fn try_from(location: &kachaka_api::Location) -> Result<Self, Self::Error> {
+        location
+            .pose
+            .as_ref()
+            .map(Pose::from)
+            .ok_or(KachakaApiError::NullResult)
+    }
+}
+
+impl TryFrom<&kachaka_api::Shelf> for Pose {
+    type Error = KachakaApiError;
+
+    fn try_from(shelf: &kachaka_api::Shelf) -> Result<Self, Self::Error> {
+        shelf
+            .pose
+            .as_ref()
+            .map(Pose::from)
+            .ok_or(KachakaApiError::NullResult)
+    }
+}
+
+impl From<Pose> for kachaka_api::Pose {
+    fn from(pose: Pose) -> Self {
+        kachaka_api::Pose {
+            x: pose.x,
+            y: pose.y,
+            theta: pose.theta,
+        }
+    }
+}
+
 impl From<kachaka_api::PowerSupplyStatus> for PowerSupplyStatus {
     fn from(status: kachaka_api::PowerSupplyStatus) -> Self {
         match status {
@@ -33,6 +95,15 @@ impl From<kachaka_api::PowerSupplyStatus> for PowerSupplyStatus {
     }
 }
 
+impl From<PowerSupplyStatus> for kachaka_api::PowerSupplyStatus {
+    fn from(status: PowerSupplyStatus) -> Self {
+        match status {
+            PowerSupplyStatus::Charging => kachaka_api::PowerSupplyStatus::Charging,
+            PowerSupplyStatus::Discharging => kachaka_api::PowerSupplyStatus::Discharging,
+        }
+    }
+}
+
 impl From<i32> for PowerSupplyStatus {
     fn from(status: i32) -> Self {
         kachaka_api::PowerSupplyStatus::try_from(status)
@@ -41,46 +112,298 @@ impl From<i32> for PowerSupplyStatus {
     }
 }
 
+/// Decodes a raw ROS image, reusing its buffer for 8-bit encodings.
+///
+/// `16UC1` and `mono16` become 16-bit grayscale, `32FC1` becomes RGB with the value
+/// in every channel, and `yuv422`/`uyvy` (UYVY) and `yuv422_yuy2` (YUYV) become RGB.
 impl From<kachaka_api::RosImage> for DynamicImage {
     fn from(image: kachaka_api::RosImage) -> Self {
-        match image.encoding.as_str() {
-            "rgb8" => {
-                let img_buffer = image::RgbImage::from_raw(image.width, image.height, image.data)
-                    .expect("Failed to create image buffer");
-                DynamicImage::ImageRgb8(img_buffer)
-            }
-            "rgba8" => {
-                let img_buffer = image::RgbaImage::from_raw(image.width, image.height, image.data)
-                    .expect("Failed to create image buffer");
-                DynamicImage::ImageRgba8(img_buffer)
-            }
+        let kachaka_api::RosImage {
+            height,
+            width,
+            encoding,
+            is_bigendian,
+            step,
+            mut data,
+            ..
+        } = image;
+        let bytes_per_pixel = match encoding.as_str() {
+            "mono8" | "8UC1" => 1,
+            "mono16" | "16UC1" | "yuv422" | "uyvy" | "yuv422_yuy2" | "yuyv" => 2,
+            "rgb8" | "bgr8" => 3,
+            "rgba8" | "bgra8" | "32FC1" => 4,
+            _ => panic!("Unsupported image encoding: {}", encoding),
+        };
+        let row_bytes = width as usize * bytes_per_pixel;
+        remove_row_padding(&mut data, height as usize, row_bytes, step as usize);
+        match encoding.as_str() {
+            "mono8" | "8UC1" => DynamicImage::ImageLuma8(
+                image::GrayImage::from_raw(width, height, data)
+                    .expect("Failed to create image buffer"),
+            ),
+            "rgb8" => DynamicImage::ImageRgb8(
+                image::RgbImage::from_raw(width, height, data)
+                    .expect("Failed to create image buffer"),
+            ),
+            "rgba8" => DynamicImage::ImageRgba8(
+                image::RgbaImage::from_raw(width, height, data)
+                    .expect("Failed to create image buffer"),
+            ),
             "bgr8" => {
-                let mut rgb_data = image.data.clone();
-                for pixel in rgb_data.chunks_mut(3) {
+                for pixel in data.chunks_exact_mut(3) {
                     pixel.swap(0, 2);
                 }
-                let img_buffer = image::RgbImage::from_raw(image.width, image.height, rgb_data)
-                    .expect("Failed to create image buffer");
-                DynamicImage::ImageRgb8(img_buffer)
+                DynamicImage::ImageRgb8(
+                    image::RgbImage::from_raw(width, height, data)
+                        .expect("Failed to create image buffer"),
+                )
+            }
+            "bgra8" => {
+                for pixel in data.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+                DynamicImage::ImageRgba8(
+                    image::RgbaImage::from_raw(width, height, data)
+                        .expect("Failed to create image buffer"),
+                )
+            }
+            "mono16" | "16UC1" => {
+                let pixels = data
+                    .chunks_exact(2)
+                    .map(|bytes| {
+                        let bytes = [bytes[0], bytes[1]];
+                        if is_bigendian {
+                            u16::from_be_bytes(bytes)
+                        } else {
+                            u16::from_le_bytes(bytes)
+                        }
+                    })
+                    .collect();
+                DynamicImage::ImageLuma16(
+                    image::ImageBuffer::from_raw(width, height, pixels)
+                        .expect("Failed to create image buffer"),
+                )
+            }
+            "32FC1" => {
+                let pixels = data
+                    .chunks_exact(4)
+                    .flat_map(|bytes| {
+                        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+                        let value = if is_bigendian {
+                            f32::from_be_bytes(bytes)
+                        } else {
+                            f32::from_le_bytes(bytes)
+                        };
+                        [value; 3]
+                    })
+                    .collect();
+                DynamicImage::ImageRgb32F(
+                    image::ImageBuffer::from_raw(width, height, pixels)
+                        .expect("Failed to create image buffer"),
+                )
+            }
+            "yuv422" | "uyvy" => {
+                yuv422_to_rgb8_in_place(&mut data, [1, 0, 3, 2]);
+                DynamicImage::ImageRgb8(
+                    image::RgbImage::from_raw(width, height, data)
+                        .expect("Failed to create image buffer"),
+                )
+            }
+            _ => {
+                // yuv422_yuy2 / yuyv
+                yuv422_to_rgb8_in_place(&mut data, [0, 1, 2, 3]);
+                DynamicImage::ImageRgb8(
+                    image::RgbImage::from_raw(width, height, data)
+                        .expect("Failed to create image buffer"),
+                )
             }
-            _ => panic!("Unsupported image encoding: {}", image.encoding),
         }
     }
 }
 
+/// Moves the rows together so that `data` holds exactly `height` rows of `row_bytes`.
+fn remove_row_padding(data: &mut Vec<u8>, height: usize, row_bytes: usize, step: usize) {
+    let step = if step == 0 { row_bytes } else { step };
+    assert!(
+        step >= row_bytes && data.len() >= step * height.saturating_sub(1) + row_bytes,
+        "Image data is smaller than its step and height"
+    );
+    if step != row_bytes {
+        for row in 1..height {
+            data.copy_within(row * step..row * step + row_bytes, row * row_bytes);
+        }
+    }
+    data.truncate(height * row_bytes);
+}
+
+/// Expands packed 4:2:2 pixel pairs to RGB, working backwards from the end so that the
+/// output never overwrites input that has not been read yet.
+///
+/// `order` gives the positions of Y0, U, Y1 and V within each 4-byte pair.
+fn yuv422_to_rgb8_in_place(data: &mut Vec<u8>, order: [usize; 4]) {
+    let pairs = data.len() / 4;
+    data.resize(pairs * 6, 0);
+    for pair in (0..pairs).rev() {
+        let input = &data[pair * 4..pair * 4 + 4];
+        let (y0, u, y1, v) = (
+            input[order[0]],
+            input[order[1]],
+            input[order[2]],
+            input[order[3]],
+        );
+        let output = &mut data[pair * 6..pair * 6 + 6];
+        output[..3].copy_from_slice(&yuv_to_rgb(y0, u, v));
+        output[3..].copy_from_slice(&yuv_to_rgb(y1, u, v));
+    }
+}
+
+/// BT.601 limited range, as produced by most USB cameras.
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> [u8; 3] {
+    let c = (y as i32 - 16) * 298;
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+    let clamp = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+    [
+        clamp(c + 409 * e),
+        clamp(c - 100 * d - 208 * e),
+        clamp(c + 516 * d),
+    ]
+}
+
 impl From<kachaka_api::RosCompressedImage> for DynamicImage {
     fn from(image: kachaka_api::RosCompressedImage) -> Self {
         image::load_from_memory(&image.data).expect("Failed to decode compressed image")
     }
 }
 
+impl From<Command> for kachaka_api::command::Command {
+    fn from(command: Command) -> Self {
+        use kachaka_api::command::Command as ProtoCommand;
+        match command {
+            Command::MoveShelf {
+                shelf_id,
+                location_id,
+            } => ProtoCommand::MoveShelfCommand(kachaka_api::MoveShelfCommand {
+                target_shelf_id: shelf_id,
+                destination_location_id: location_id,
+            }),
+            Command::ReturnShelf { shelf_id } => {
+                ProtoCommand::ReturnShelfCommand(kachaka_api::ReturnShelfCommand {
+                    target_shelf_id: shelf_id,
+                })
+            }
+            Command::UndockShelf { shelf_id } => {
+                ProtoCommand::UndockShelfCommand(kachaka_api::UndockShelfCommand {
+                    target_shelf_id: shelf_id,
+                })
+            }
+            Command::MoveToLocation { location_id } => {
+                ProtoCommand::MoveToLocationCommand(kachaka_api::MoveToLocationCommand {
+                    target_location_id: location_id,
+                })
+            }
+            Command::ReturnHome => {
+                ProtoCommand::ReturnHomeCommand(kachaka_api::ReturnHomeCommand {})
+            }
+            Command::DockShelf => ProtoCommand::DockShelfCommand(kachaka_api::DockShelfCommand {}),
+            Command::Speak { text } => {
+                ProtoCommand::SpeakCommand(kachaka_api::SpeakCommand { text })
+            }
+            Command::MoveToPose { x, y, yaw } => {
+                ProtoCommand::MoveToPoseCommand(kachaka_api::MoveToPoseCommand { x, y, yaw })
+            }
+            Command::Lock { duration_sec } => {
+                ProtoCommand::LockCommand(kachaka_api::LockCommand { duration_sec })
+            }
+            Command::MoveForward {
+                distance_meter,
+                speed,
+            } => ProtoCommand::MoveForwardCommand(kachaka_api::MoveForwardCommand {
+                distance_meter,
+                speed,
+            }),
+            Command::RotateInPlace { angle_radian } => {
+                ProtoCommand::RotateInPlaceCommand(kachaka_api::RotateInPlaceCommand {
+                    angle_radian,
+                })
+            }
+            Command::DockAnyShelfWithRegistration {
+                location_id,
+                dock_forward,
+            } => ProtoCommand::DockAnyShelfWithRegistrationCommand(
+                kachaka_api::DockAnyShelfWithRegistrationCommand {
+                    target_location_id: location_id,
+                    dock_forward,
+                },
+            ),
+        }
+    }
+}
+
+impl From<Command> for kachaka_api::Command {
+    fn from(command: Command) -> Self {
+        kachaka_api::Command {
+            command: Some(command.into()),
+        }
+    }
+}
+
+impl From<kachaka_api::command::Command> for Command {
+    fn from(command: kachaka_api::command::Command) -> Self {
+        use kachaka_api::command::Command as ProtoCommand;
+        match command {
+            ProtoCommand::MoveShelfCommand(command) => Command::MoveShelf {
+                shelf_id: command.target_shelf_id,
+                location_id: command.destination_location_id,
+            },
+            ProtoCommand::ReturnShelfCommand(command) => Command::ReturnShelf {
+                shelf_id: command.target_shelf_id,
+            },
+            ProtoCommand::UndockShelfCommand(command) => Command::UndockShelf {
+                shelf_id: command.target_shelf_id,
+            },
+            ProtoCommand::MoveToLocationCommand(command) => Command::MoveToLocation {
+                location_id: command.target_location_id,
+            },
+            ProtoCommand::ReturnHomeCommand(_) => Command::ReturnHome,
+            ProtoCommand::DockShelfCommand(_) => Command::DockShelf,
+            ProtoCommand::SpeakCommand(command) => Command::Speak { text: command.text },
+            ProtoCommand::MoveToPoseCommand(command) => Command::MoveToPose {
+                x: command.x,
+                y: command.y,
+                yaw: command.yaw,
+            },
+            ProtoCommand::LockCommand(command) => Command::Lock {
+                duration_sec: command.duration_sec,
+            },
+            ProtoCommand::MoveForwardCommand(command) => Command::MoveForward {
+                distance_meter: command.distance_meter,
+                speed: command.speed,
+            },
+            ProtoCommand::RotateInPlaceCommand(command) => Command::RotateInPlace {
+                angle_radian: command.angle_radian,
+            },
+            ProtoCommand::DockAnyShelfWithRegistrationCommand(command) => {
+                Command::DockAnyShelfWithRegistration {
+                    location_id: command.target_location_id,
+                    dock_forward: command.dock_forward,
+                }
+            }
+        }
+    }
+}
+
 impl From<kachaka_api::GetCommandStateResponse> for CommandState {
     fn from(get_command_state_response: kachaka_api::GetCommandStateResponse) -> Self {
         match kachaka_api::CommandState::try_from(get_command_state_response.state).unwrap() {
             kachaka_api::CommandState::Unspecified => CommandState::Unspecified,
             kachaka_api::CommandState::Pending => CommandState::Pending,
             kachaka_api::CommandState::Running => CommandState::Running(
-                get_command_state_response.command.unwrap(),
+                get_command_state_response
+                    .command
+                    .and_then(|command| command.command)
+                    .unwrap()
+                    .into(),
                 get_command_state_response.command_id,
             ),
         }
@@ -90,10 +413,13 @@ impl From<kachaka_api::GetCommandStateResponse> for CommandState {
 impl From<kachaka_api::GetLastCommandResultResponse> for Option<CommandResult> {
     fn from(response: kachaka_api::GetLastCommandResultResponse) -> Self {
         response.result.and_then(|result| {
-            response.command.map(|command| CommandResult {
-                command,
-                result: result.into(),
-            })
+            response
+                .command
+                .and_then(|command| command.command)
+                .map(|command| CommandResult {
+                    command: command.into(),
+                    result: result.into(),
+                })
         })
     }
 }
@@ -0,0 +1,388 @@
+use futures::stream::{Stream, StreamExt};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::fleet::Fleet;
+use crate::mission::{Mission, MissionEvent, MissionStep};
+use crate::types::{BatteryInfo, Command, CommandState, Pose};
+use crate::{KachakaApiClient, StartCommandOptions};
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+pub type TaskId = u64;
+
+/// A command to run on whichever robot of the fleet fits best.
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub command: Command,
+    pub options: StartCommandOptions,
+    /// How many times the task is handed to another robot after a failure.
+    pub max_reassignments: u32,
+}
+
+impl Task {
+    pub fn new(command: Command) -> Self {
+        Self {
+            command,
+            options: StartCommandOptions::default(),
+            max_reassignments: 2,
+        }
+    }
+
+    pub fn options(mut self, options: StartCommandOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn max_reassignments(mut self, max_reassignments: u32) -> Self {
+        self.max_reassignments = max_reassignments;
+        self
+    }
+}
+
+/// The state of a connected, not yet assigned robot, as seen by a [`CostFunction`].
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub robot_id: String,
+    pub pose: Pose,
+    pub battery_info: BatteryInfo,
+    pub command_state: CommandState,
+    /// Empty when the robot does not hold a shelf.
+    pub moving_shelf_id: String,
+    /// Where the robot has to go first: the shelf to pick up, or the destination of
+    /// a move command. Looked up in this robot's layout.
+    pub target_pose: Option<Pose>,
+}
+
+pub trait CostFunction: Send + Sync + 'static {
+    /// Returns `None` when the robot cannot take the task; otherwise the robot with the
+    /// lowest cost is chosen.
+    fn cost(&self, task: &Task, candidate: &Candidate) -> Option<f64>;
+}
+
+impl<F> CostFunction for F
+where
+    F: Fn(&Task, &Candidate) -> Option<f64> + Send + Sync + 'static,
+{
+    fn cost(&self, task: &Task, candidate: &Candidate) -> Option<f64> {
+        self(task, candidate)
+    }
+}
+
+/// Picks the nearest idle robot with enough battery that does not hold another shelf.
+#[derive(Debug, Clone)]
+pub struct DefaultCostFunction {
+    pub min_battery_percentage: f64,
+}
+
+impl Default for DefaultCostFunction {
+    fn default() -> Self {
+        Self {
+            min_battery_percentage: 30.0,
+        }
+    }
+}
+
+impl CostFunction for DefaultCostFunction {
+    fn cost(&self, task: &Task, candidate: &Candidate) -> Option<f64> {
+        if candidate.command_state != CommandState::Unspecified
+            || candidate.battery_info.remaining_percentage < self.min_battery_percentage
+        {
+            return None;
+        }
+        let task_shelf_id = match &task.command {
+            Command::MoveShelf { shelf_id, .. } | Command::ReturnShelf { shelf_id } => {
+                Some(shelf_id.as_str())
+            }
+            _ => None,
+        };
+        if !candidate.moving_shelf_id.is_empty()
+            && Some(candidate.moving_shelf_id.as_str()) != task_shelf_id
+        {
+            return None;
+        }
+        Some(
+            candidate
+                .target_pose
+                .as_ref()
+                .map_or(0.0, |target| candidate.pose.distance(target)),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum DispatchEvent {
+    Queued {
+        task_id: TaskId,
+    },
+    Assigned {
+        task_id: TaskId,
+        robot_id: String,
+        command_id: String,
+    },
+    Completed {
+        task_id: TaskId,
+        robot_id: String,
+    },
+    /// The robot failed the task; it is queued again unless it was the last attempt.
+    Failed {
+        task_id: TaskId,
+        robot_id: String,
+        error: String,
+    },
+    Abandoned {
+        task_id: TaskId,
+    },
+}
+
+struct QueuedTask {
+    task_id: TaskId,
+    task: Task,
+    attempts: u32,
+    /// Robots that already failed this task.
+    excluded: HashSet<String>,
+}
+
+enum Message {
+    Submit(QueuedTask),
+    Finished {
+        queued: QueuedTask,
+        robot_id: String,
+        succeeded: bool,
+    },
+}
+
+/// Assigns submitted tasks to robots of a [`Fleet`].
+///
+/// Tasks are kept in submission order and assigned as soon as a robot qualifies; a
+/// robot runs one dispatched task at a time. A task that fails is put back at the
+/// front of the queue and is not given to the same robot again.
+pub struct Dispatcher {
+    tx: mpsc::UnboundedSender<Message>,
+    events: broadcast::Sender<DispatchEvent>,
+    next_task_id: AtomicU64,
+    task: JoinHandle<()>,
+}
+
+impl Dispatcher {
+    pub fn new(fleet: Arc<Fleet>, cost_function: impl CostFunction) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (events, _) = broadcast::channel(256);
+        let runner = DispatcherRunner {
+            fleet,
+            cost_function: Box::new(cost_function),
+            queue: VecDeque::new(),
+            busy: HashSet::new(),
+            tx: tx.clone(),
+            events: events.clone(),
+        };
+        Self {
+            tx,
+            events,
+            next_task_id: AtomicU64::new(1),
+            task: tokio::spawn(runner.run(rx)),
+        }
+    }
+
+    pub fn submit(&self, task: Task) -> TaskId {
+        let task_id = self.next_task_id.fetch_add(1, Ordering::Relaxed);
+        let _ = self.tx.send(Message::Submit(QueuedTask {
+            task_id,
+            task,
+            attempts: 0,
+            excluded: HashSet::new(),
+        }));
+        task_id
+    }
+
+    /// Events of all tasks submitted after the call; slow consumers may miss events.
+    pub fn events(&self) -> impl Stream<Item = DispatchEvent> {
+        BroadcastStream::new(self.events.subscribe())
+            .filter_map(|event| futures::future::ready(event.ok()))
+    }
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct DispatcherRunner {
+    fleet: Arc<Fleet>,
+    cost_function: Box<dyn CostFunction>,
+    queue: VecDeque<QueuedTask>,
+    busy: HashSet<String>,
+    tx: mpsc::UnboundedSender<Message>,
+    events: broadcast::Sender<DispatchEvent>,
+}
+
+impl DispatcherRunner {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<Message>) {
+        let mut retry = tokio::time::interval(RETRY_INTERVAL);
+        loop {
+            tokio::select! {
+                message = rx.recv() => match message {
+                    Some(message) => self.handle(message),
+                    None => break,
+                },
+                _ = retry.tick() => {}
+            }
+            self.assign_pending().await;
+        }
+    }
+
+    fn handle(&mut self, message: Message) {
+        match message {
+            Message::Submit(queued) => {
+                self.emit(DispatchEvent::Queued {
+                    task_id: queued.task_id,
+                });
+                self.queue.push_back(queued);
+            }
+            Message::Finished {
+                mut queued,
+                robot_id,
+                succeeded,
+            } => {
+                self.busy.remove(&robot_id);
+                if succeeded {
+                    self.emit(DispatchEvent::Completed {
+                        task_id: queued.task_id,
+                        robot_id,
+                    });
+                } else if queued.attempts > queued.task.max_reassignments {
+                    self.emit(DispatchEvent::Abandoned {
+                        task_id: queued.task_id,
+                    });
+                } else {
+                    queued.excluded.insert(robot_id);
+                    self.queue.push_front(queued);
+                }
+            }
+        }
+    }
+
+    async fn assign_pending(&mut self) {
+        let mut waiting = VecDeque::new();
+        let robot_count = self.fleet.robot_ids().len();
+        while let Some(queued) = self.queue.pop_front() {
+            if queued.excluded.len() >= robot_count {
+                self.emit(DispatchEvent::Abandoned {
+                    task_id: queued.task_id,
+                });
+                continue;
+            }
+            match self.choose_robot(&queued).await {
+                Some(robot_id) => self.start(queued, robot_id).await,
+                None => waiting.push_back(queued),
+            }
+        }
+        self.queue = waiting;
+    }
+
+    async fn choose_robot(&self, queued: &QueuedTask) -> Option<String> {
+        let mut best: Option<(f64, String)> = None;
+        for robot_id in self.fleet.robot_ids() {
+            if self.busy.contains(&robot_id) || queued.excluded.contains(&robot_id) {
+                continue;
+            }
+            let Some(candidate) = self.candidate(&robot_id, &queued.task).await else {
+                continue;
+            };
+            if let Some(cost) = self.cost_function.cost(&queued.task, &candidate) {
+                if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+                    best = Some((cost, robot_id));
+                }
+            }
+        }
+        best.map(|(_, robot_id)| robot_id)
+    }
+
+    async fn candidate(&self, robot_id: &str, task: &Task) -> Option<Candidate> {
+        if !self.fleet.health(robot_id)?.connected {
+            return None;
+        }
+        let client = self.fleet.client(robot_id)?;
+        Some(Candidate {
+            robot_id: robot_id.to_string(),
+            pose: self.fleet.robot_pose(robot_id)?,
+            battery_info: self.fleet.battery_info(robot_id)?,
+            command_state: self.fleet.command_state(robot_id)?,
+            moving_shelf_id: self.fleet.moving_shelf_id(robot_id)?,
+            target_pose: target_pose(client, &task.command).await,
+        })
+    }
+
+    async fn start(&mut self, mut queued: QueuedTask, robot_id: String) {
+        let Some(client) = self.fleet.client(&robot_id) else {
+            self.queue.push_back(queued);
+            return;
+        };
+        queued.attempts += 1;
+        self.busy.insert(robot_id.clone());
+        let step =
+            MissionStep::new(queued.task.command.clone()).options(queued.task.options.clone());
+        let mut mission_events = Box::pin(Mission::new().step(step).run(client).await);
+        let (tx, events) = (self.tx.clone(), self.events.clone());
+        tokio::spawn(async move {
+            let mut succeeded = false;
+            while let Some(event) = mission_events.next().await {
+                match event {
+                    MissionEvent::StepStarted { command_id, .. } => {
+                        let _ = events.send(DispatchEvent::Assigned {
+                            task_id: queued.task_id,
+                            robot_id: robot_id.clone(),
+                            command_id,
+                        });
+                    }
+                    MissionEvent::StepSucceeded { .. } => succeeded = true,
+                    MissionEvent::StepFailed { error, .. } => {
+                        let _ = events.send(DispatchEvent::Failed {
+                            task_id: queued.task_id,
+                            robot_id: robot_id.clone(),
+                            error: format!("{:?}", error),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            let _ = tx.send(Message::Finished {
+                queued,
+                robot_id,
+                succeeded,
+            });
+        });
+    }
+
+    fn emit(&self, event: DispatchEvent) {
+        let _ = self.events.send(event);
+    }
+}
+
+async fn target_pose(mut client: KachakaApiClient, command: &Command) -> Option<Pose> {
+    let (shelf_id, location_id) = match command {
+        Command::MoveShelf { shelf_id, .. } | Command::ReturnShelf { shelf_id } => {
+            (Some(shelf_id), None)
+        }
+        Command::MoveToLocation { location_id } => (None, Some(location_id)),
+        Command::MoveToPose { x, y, yaw } => return Some(Pose::new(*x, *y, *yaw)),
+        _ => return None,
+    };
+    if let Some(shelf_id) = shelf_id {
+        let shelves = client.get_latest_shelves().await.ok()?;
+        let shelf = shelves.into_iter().find(|shelf| &shelf.id == shelf_id)?;
+        return Pose::try_from(&shelf).ok();
+    }
+    let location_id = location_id?;
+    let locations = client.get_latest_locations().await.ok()?;
+    let location = locations
+        .into_iter()
+        .find(|location| &location.id == location_id)?;
+    Pose::try_from(&location).ok()
+}
@@ -0,0 +1,356 @@
+use futures::future::{self, join_all};
+use futures::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::WatchStream;
+use tonic::transport::Endpoint;
+
+use crate::types::{BatteryInfo, Command, CommandState, KachakaApiError, KachakaError, Pose};
+use crate::{KachakaApiClient, StartCommandOptions};
+
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub enum FleetError {
+    IoError(std::io::Error),
+    YamlParseError(serde_yaml::Error),
+    TomlParseError(toml::de::Error),
+    UnsupportedExtension(String),
+    InvalidUrl {
+        robot_id: String,
+        error: tonic::transport::Error,
+    },
+    DuplicateRobotId(String),
+}
+
+/// The robots of a fleet, loaded from a YAML or TOML file.
+///
+/// ```toml
+/// [[robots]]
+/// id = "kachaka-1"
+/// url = "http://kachaka-020.local:26400"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct FleetConfig {
+    pub robots: Vec<FleetRobotConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FleetRobotConfig {
+    pub id: String,
+    pub url: String,
+}
+
+impl FleetConfig {
+    pub fn from_yaml_str(s: &str) -> Result<Self, FleetError> {
+        serde_yaml::from_str(s).map_err(FleetError::YamlParseError)
+    }
+
+    pub fn from_toml_str(s: &str) -> Result<Self, FleetError> {
+        toml::from_str(s).map_err(FleetError::TomlParseError)
+    }
+
+    /// Loads a fleet config, choosing the format from the extension
+    /// (`.yaml`, `.yml` or `.toml`).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FleetError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(FleetError::IoError)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => Self::from_yaml_str(&content),
+            Some("toml") => Self::from_toml_str(&content),
+            extension => Err(FleetError::UnsupportedExtension(
+                extension.unwrap_or_default().to_string(),
+            )),
+        }
+    }
+}
+
+/// A value reported by one robot of a [`Fleet`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct RobotUpdate<T> {
+    pub robot_id: String,
+    pub value: T,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotHealth {
+    /// Whether the latest request to the robot succeeded.
+    pub connected: bool,
+    /// When the robot last answered any subscription.
+    pub last_seen: Option<Instant>,
+    pub errors: Vec<KachakaError>,
+    pub battery_info: Option<BatteryInfo>,
+}
+
+struct FleetRobot {
+    client: KachakaApiClient,
+    health: watch::Sender<RobotHealth>,
+    pose: watch::Sender<Option<Pose>>,
+    battery_info: watch::Sender<Option<BatteryInfo>>,
+    error: watch::Sender<Option<Vec<KachakaError>>>,
+    command_state: watch::Sender<Option<CommandState>>,
+    moving_shelf_id: watch::Sender<Option<String>>,
+}
+
+/// Named connections to several robots.
+///
+/// Every robot is watched once per topic in background tasks; all the streams and
+/// health reports handed out by the fleet share those subscriptions. A subscription
+/// that fails is started again after a short delay. The tasks stop when the fleet is
+/// dropped.
+pub struct Fleet {
+    robots: BTreeMap<String, FleetRobot>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Fleet {
+    pub fn new(robots: impl IntoIterator<Item = (String, KachakaApiClient)>) -> Self {
+        let mut fleet = Self {
+            robots: BTreeMap::new(),
+            tasks: Vec::new(),
+        };
+        for (robot_id, client) in robots {
+            fleet.add_robot(robot_id, client);
+        }
+        fleet
+    }
+
+    /// Connects lazily to every robot of the config, so offline robots do not prevent
+    /// the fleet from starting.
+    pub fn from_config(config: &FleetConfig) -> Result<Self, FleetError> {
+        let mut robots = BTreeMap::new();
+        for robot in &config.robots {
+            if robots.contains_key(&robot.id) {
+                return Err(FleetError::DuplicateRobotId(robot.id.clone()));
+            }
+            let channel = Endpoint::from_shared(robot.url.clone())
+                .map_err(|error| FleetError::InvalidUrl {
+                    robot_id: robot.id.clone(),
+                    error,
+                })?
+                .connect_lazy();
+            robots.insert(robot.id.clone(), KachakaApiClient::new(channel));
+        }
+        Ok(Self::new(robots))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FleetError> {
+        Self::from_config(&FleetConfig::load(path)?)
+    }
+
+    fn add_robot(&mut self, robot_id: String, client: KachakaApiClient) {
+        let robot = FleetRobot {
+            client,
+            health: watch::channel(RobotHealth::default()).0,
+            pose: watch::channel(None).0,
+            battery_info: watch::channel(None).0,
+            error: watch::channel(None).0,
+            command_state: watch::channel(None).0,
+            moving_shelf_id: watch::channel(None).0,
+        };
+        self.tasks.push(spawn_subscription(
+            &robot,
+            robot.pose.clone(),
+            |mut client| async move { client.watch_robot_pose().await },
+            |_, _| {},
+        ));
+        self.tasks.push(spawn_subscription(
+            &robot,
+            robot.battery_info.clone(),
+            |mut client| async move { client.watch_battery_info().await },
+            |health, battery_info| health.battery_info = Some(battery_info.clone()),
+        ));
+        self.tasks.push(spawn_subscription(
+            &robot,
+            robot.error.clone(),
+            |mut client| async move { client.watch_error().await },
+            |health, errors| health.errors = errors.clone(),
+        ));
+        self.tasks.push(spawn_subscription(
+            &robot,
+            robot.command_state.clone(),
+            |mut client| async move { client.watch_command_state().await },
+            |_, _| {},
+        ));
+        self.tasks.push(spawn_subscription(
+            &robot,
+            robot.moving_shelf_id.clone(),
+            |mut client| async move { client.watch_moving_shelf_id().await },
+            |_, _| {},
+        ));
+        self.robots.insert(robot_id, robot);
+    }
+
+    pub fn robot_ids(&self) -> Vec<String> {
+        self.robots.keys().cloned().collect()
+    }
+
+    pub fn client(&self, robot_id: &str) -> Option<KachakaApiClient> {
+        self.robots.get(robot_id).map(|robot| robot.client.clone())
+    }
+
+    pub fn health(&self, robot_id: &str) -> Option<RobotHealth> {
+        self.robots
+            .get(robot_id)
+            .map(|robot| robot.health.borrow().clone())
+    }
+
+    // latest values, `None` until the robot has answered
+    pub fn robot_pose(&self, robot_id: &str) -> Option<Pose> {
+        self.robots.get(robot_id)?.pose.borrow().clone()
+    }
+
+    pub fn battery_info(&self, robot_id: &str) -> Option<BatteryInfo> {
+        self.robots.get(robot_id)?.battery_info.borrow().clone()
+    }
+
+    pub fn command_state(&self, robot_id: &str) -> Option<CommandState> {
+        self.robots.get(robot_id)?.command_state.borrow().clone()
+    }
+
+    pub fn moving_shelf_id(&self, robot_id: &str) -> Option<String> {
+        self.robots.get(robot_id)?.moving_shelf_id.borrow().clone()
+    }
+
+    pub fn watch_health(&self) -> impl Stream<Item = RobotUpdate<RobotHealth>> {
+        self.merge(|robot| robot.health.subscribe())
+    }
+
+    /// The latest pose of every robot, followed by every change.
+    pub fn watch_robot_pose(&self) -> impl Stream<Item = RobotUpdate<Pose>> {
+        self.merge_known(|robot| robot.pose.subscribe())
+    }
+
+    pub fn watch_battery_info(&self) -> impl Stream<Item = RobotUpdate<BatteryInfo>> {
+        self.merge_known(|robot| robot.battery_info.subscribe())
+    }
+
+    pub fn watch_error(&self) -> impl Stream<Item = RobotUpdate<Vec<KachakaError>>> {
+        self.merge_known(|robot| robot.error.subscribe())
+    }
+
+    pub fn watch_command_state(&self) -> impl Stream<Item = RobotUpdate<CommandState>> {
+        self.merge_known(|robot| robot.command_state.subscribe())
+    }
+
+    pub fn watch_moving_shelf_id(&self) -> impl Stream<Item = RobotUpdate<String>> {
+        self.merge_known(|robot| robot.moving_shelf_id.subscribe())
+    }
+
+    fn merge<T: Clone + Send + Sync + 'static>(
+        &self,
+        subscribe: impl Fn(&FleetRobot) -> watch::Receiver<T>,
+    ) -> impl Stream<Item = RobotUpdate<T>> {
+        stream::select_all(self.robots.iter().map(|(robot_id, robot)| {
+            let robot_id = robot_id.clone();
+            WatchStream::new(subscribe(robot)).map(move |value| RobotUpdate {
+                robot_id: robot_id.clone(),
+                value,
+            })
+        }))
+    }
+
+    fn merge_known<T: Clone + Send + Sync + 'static>(
+        &self,
+        subscribe: impl Fn(&FleetRobot) -> watch::Receiver<Option<T>>,
+    ) -> impl Stream<Item = RobotUpdate<T>> {
+        self.merge(subscribe).filter_map(|update| {
+            future::ready(update.value.map(|value| RobotUpdate {
+                robot_id: update.robot_id,
+                value,
+            }))
+        })
+    }
+
+    /// Starts the same command on every robot concurrently.
+    pub async fn broadcast_command(
+        &self,
+        command: Command,
+        options: StartCommandOptions,
+    ) -> BTreeMap<String, Result<String, KachakaApiError>> {
+        self.for_each_robot(|mut client| {
+            let (command, options) = (command.clone(), options.clone());
+            async move { client.start_command(command, options).await }
+        })
+        .await
+    }
+
+    pub async fn cancel_all_commands(&self) -> BTreeMap<String, Result<(), KachakaApiError>> {
+        self.for_each_robot(|mut client| async move { client.cancel_command().await })
+            .await
+    }
+
+    async fn for_each_robot<T, F, Fut>(&self, f: F) -> BTreeMap<String, T>
+    where
+        F: Fn(KachakaApiClient) -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let results = join_all(self.robots.values().map(|robot| f(robot.client.clone()))).await;
+        self.robots.keys().cloned().zip(results).collect()
+    }
+}
+
+impl Drop for Fleet {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+fn spawn_subscription<T, S, F, Fut>(
+    robot: &FleetRobot,
+    latest: watch::Sender<Option<T>>,
+    subscribe: F,
+    update_health: fn(&mut RobotHealth, &T),
+) -> JoinHandle<()>
+where
+    T: Send + Sync + 'static,
+    S: Stream<Item = Result<T, KachakaApiError>> + Send,
+    F: Fn(KachakaApiClient) -> Fut + Send + 'static,
+    Fut: Future<Output = S> + Send,
+{
+    let client = robot.client.clone();
+    let health = robot.health.clone();
+    tokio::spawn(async move {
+        loop {
+            let mut stream = Box::pin(subscribe(client.clone()).await);
+            while let Some(item) = stream.next().await {
+                match item {
+                    Ok(value) => {
+                        // `last_seen` alone does not wake up health watchers.
+                        health.send_if_modified(|health| {
+                            let before = (
+                                health.connected,
+                                health.errors.clone(),
+                                health.battery_info.clone(),
+                            );
+                            health.connected = true;
+                            health.last_seen = Some(Instant::now());
+                            update_health(health, &value);
+                            before
+                                != (
+                                    health.connected,
+                                    health.errors.clone(),
+                                    health.battery_info.clone(),
+                                )
+                        });
+                        latest.send_replace(Some(value));
+                    }
+                    Err(_) => {
+                        health.send_if_modified(|health| {
+                            std::mem::replace(&mut health.connected, false)
+                        });
+                        break;
+                    }
+                }
+            }
+            tokio::time::sleep(RESUBSCRIBE_INTERVAL).await;
+        }
+    })
+}
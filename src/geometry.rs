@@ -0,0 +1,71 @@
+use std::f64::consts::PI;
+
+use crate::types::Pose;
+
+/// Wraps an angle into `(-π, π]`.
+pub fn normalize_angle(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(2.0 * PI);
+    if angle > PI {
+        angle - 2.0 * PI
+    } else {
+        angle
+    }
+}
+
+/// The shortest signed rotation from `from` to `to`, in `(-π, π]`.
+pub fn angle_difference(to: f64, from: f64) -> f64 {
+    normalize_angle(to - from)
+}
+
+/// SE(2) operations; `theta` is kept in `(-π, π]` by every operation.
+impl Pose {
+    pub fn new(x: f64, y: f64, theta: f64) -> Self {
+        Self { x, y, theta }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+
+    /// Applies `other`, expressed in the frame of `self`: `self ⊕ other`.
+    pub fn compose(&self, other: &Pose) -> Pose {
+        let (x, y) = self.transform_point(other.x, other.y);
+        Pose::new(x, y, normalize_angle(self.theta + other.theta))
+    }
+
+    pub fn inverse(&self) -> Pose {
+        let (sin, cos) = self.theta.sin_cos();
+        Pose::new(
+            -(cos * self.x + sin * self.y),
+            sin * self.x - cos * self.y,
+            normalize_angle(-self.theta),
+        )
+    }
+
+    /// This pose expressed in the frame of `origin`, so that
+    /// `origin.compose(&self.relative_to(origin))` is `self`.
+    pub fn relative_to(&self, origin: &Pose) -> Pose {
+        origin.inverse().compose(self)
+    }
+
+    /// Maps a point from the frame of this pose to the parent frame.
+    pub fn transform_point(&self, x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = self.theta.sin_cos();
+        (self.x + cos * x - sin * y, self.y + sin * x + cos * y)
+    }
+
+    /// Euclidean distance between the positions, ignoring the orientation.
+    pub fn distance(&self, other: &Pose) -> f64 {
+        (other.x - self.x).hypot(other.y - self.y)
+    }
+
+    /// The pose at `t` (0.0 = `self`, 1.0 = `other`) on the straight line between the
+    /// positions, turning along the shortest direction.
+    pub fn interpolate(&self, other: &Pose, t: f64) -> Pose {
+        Pose::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+            normalize_angle(self.theta + angle_difference(other.theta, self.theta) * t),
+        )
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::result_large_err)]
+
 use futures::stream::Stream;
 use image::DynamicImage;
 use kachaka_api::kachaka_api_client::KachakaApiClient as TonicKachakaApiClient;
@@ -8,13 +10,37 @@ pub mod kachaka_api {
 }
 
 pub mod api_impl;
+pub mod battery;
+pub mod camera;
 pub mod conversion;
+#[cfg(feature = "fleet")]
+pub mod dispatcher;
+#[cfg(feature = "fleet")]
+pub mod fleet;
+pub mod geometry;
+pub mod mission;
+#[cfg(feature = "mission-file")]
+pub mod mission_file;
+#[cfg(feature = "mjpeg")]
+pub mod mjpeg;
 pub mod options;
+pub mod path_planner;
+pub mod recording;
+pub mod robot_state;
 pub mod shelf_location_resolver;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod types;
+pub mod video;
+#[cfg(feature = "zones")]
+pub mod zones;
 
+pub use camera::{CameraFrame, FrameDecoding};
 pub use options::StartCommandOptions;
-pub use types::{BatteryInfo, CommandResult, CommandState, KachakaApiError, KachakaError, Pose};
+pub use types::{
+    BatteryInfo, Camera, Command, CommandResult, CommandState, KachakaApiError, KachakaError, Pose,
+    Versioned,
+};
 
 #[derive(Clone)]
 pub struct KachakaApiClient {
@@ -22,6 +48,12 @@ pub struct KachakaApiClient {
 }
 
 impl KachakaApiClient {
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            client: TonicKachakaApiClient::new(channel),
+        }
+    }
+
     pub async fn connect<D>(target: D) -> Result<Self, tonic::transport::Error>
     where
         D: std::convert::TryInto<tonic::transport::Endpoint>,
@@ -50,6 +82,20 @@ impl KachakaApiClient {
         api_impl::watch_robot_serial_number(&mut self.client).await
     }
 
+    pub async fn get_robot_serial_number_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<String>, KachakaApiError> {
+        api_impl::get_robot_serial_number_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_robot_serial_number_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<String>, KachakaApiError>> {
+        api_impl::watch_robot_serial_number_from(&mut self.client, cursor).await
+    }
+
     // GetRobotVersion
     pub async fn get_robot_version(&mut self, cursor: i64) -> Result<String, KachakaApiError> {
         api_impl::get_robot_version(&mut self.client, cursor).await
@@ -65,6 +111,20 @@ impl KachakaApiClient {
         api_impl::watch_robot_version(&mut self.client).await
     }
 
+    pub async fn get_robot_version_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<String>, KachakaApiError> {
+        api_impl::get_robot_version_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_robot_version_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<String>, KachakaApiError>> {
+        api_impl::watch_robot_version_from(&mut self.client, cursor).await
+    }
+
     // GetRobotPose
     pub async fn get_robot_pose(&mut self, cursor: i64) -> Result<Pose, KachakaApiError> {
         api_impl::get_robot_pose(&mut self.client, cursor).await
@@ -78,6 +138,20 @@ impl KachakaApiClient {
         api_impl::watch_robot_pose(&mut self.client).await
     }
 
+    pub async fn get_robot_pose_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<Pose>, KachakaApiError> {
+        api_impl::get_robot_pose_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_robot_pose_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<Pose>, KachakaApiError>> {
+        api_impl::watch_robot_pose_from(&mut self.client, cursor).await
+    }
+
     // GetBatteryInfo
     pub async fn get_battery_info(&mut self, cursor: i64) -> Result<BatteryInfo, KachakaApiError> {
         api_impl::get_battery_info(&mut self.client, cursor).await
@@ -93,6 +167,20 @@ impl KachakaApiClient {
         api_impl::watch_battery_info(&mut self.client).await
     }
 
+    pub async fn get_battery_info_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<BatteryInfo>, KachakaApiError> {
+        api_impl::get_battery_info_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_battery_info_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<BatteryInfo>, KachakaApiError>> {
+        api_impl::watch_battery_info_from(&mut self.client, cursor).await
+    }
+
     // GetFrontCameraRosImage
     pub async fn get_front_camera_ros_image(
         &mut self,
@@ -113,6 +201,20 @@ impl KachakaApiClient {
         api_impl::watch_front_camera_ros_image(&mut self.client).await
     }
 
+    pub async fn get_front_camera_ros_image_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<DynamicImage>, KachakaApiError> {
+        api_impl::get_front_camera_ros_image_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_front_camera_ros_image_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<DynamicImage>, KachakaApiError>> {
+        api_impl::watch_front_camera_ros_image_from(&mut self.client, cursor).await
+    }
+
     // GetFrontCameraRosCompressedImage
     pub async fn get_front_camera_ros_compressed_image(
         &mut self,
@@ -133,6 +235,20 @@ impl KachakaApiClient {
         api_impl::watch_front_camera_ros_compressed_image(&mut self.client).await
     }
 
+    pub async fn get_front_camera_ros_compressed_image_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<DynamicImage>, KachakaApiError> {
+        api_impl::get_front_camera_ros_compressed_image_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_front_camera_ros_compressed_image_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<DynamicImage>, KachakaApiError>> {
+        api_impl::watch_front_camera_ros_compressed_image_from(&mut self.client, cursor).await
+    }
+
     // GetBackCameraRosImage
     pub async fn get_back_camera_ros_image(
         &mut self,
@@ -153,6 +269,20 @@ impl KachakaApiClient {
         api_impl::watch_back_camera_ros_image(&mut self.client).await
     }
 
+    pub async fn get_back_camera_ros_image_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<DynamicImage>, KachakaApiError> {
+        api_impl::get_back_camera_ros_image_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_back_camera_ros_image_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<DynamicImage>, KachakaApiError>> {
+        api_impl::watch_back_camera_ros_image_from(&mut self.client, cursor).await
+    }
+
     // GetBackCameraRosCompressedImage
     pub async fn get_back_camera_ros_compressed_image(
         &mut self,
@@ -173,6 +303,68 @@ impl KachakaApiClient {
         api_impl::watch_back_camera_ros_compressed_image(&mut self.client).await
     }
 
+    pub async fn get_back_camera_ros_compressed_image_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<DynamicImage>, KachakaApiError> {
+        api_impl::get_back_camera_ros_compressed_image_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_back_camera_ros_compressed_image_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<DynamicImage>, KachakaApiError>> {
+        api_impl::watch_back_camera_ros_compressed_image_from(&mut self.client, cursor).await
+    }
+
+    // camera frames with their ROS header
+    pub async fn get_camera_frame(
+        &mut self,
+        camera: Camera,
+        cursor: i64,
+        decoding: FrameDecoding,
+    ) -> Result<CameraFrame, KachakaApiError> {
+        api_impl::get_camera_frame(&mut self.client, camera, cursor, decoding).await
+    }
+
+    pub async fn get_latest_camera_frame(
+        &mut self,
+        camera: Camera,
+        decoding: FrameDecoding,
+    ) -> Result<CameraFrame, KachakaApiError> {
+        api_impl::get_latest_camera_frame(&mut self.client, camera, decoding).await
+    }
+
+    pub async fn watch_camera_frames(
+        &mut self,
+        camera: Camera,
+        decoding: FrameDecoding,
+    ) -> impl Stream<Item = Result<CameraFrame, KachakaApiError>> {
+        api_impl::watch_camera_frames(&mut self.client, camera, decoding).await
+    }
+
+    pub async fn get_camera_raw_frame(
+        &mut self,
+        camera: Camera,
+        cursor: i64,
+    ) -> Result<CameraFrame, KachakaApiError> {
+        api_impl::get_camera_raw_frame(&mut self.client, camera, cursor).await
+    }
+
+    pub async fn get_latest_camera_raw_frame(
+        &mut self,
+        camera: Camera,
+    ) -> Result<CameraFrame, KachakaApiError> {
+        api_impl::get_latest_camera_raw_frame(&mut self.client, camera).await
+    }
+
+    pub async fn watch_camera_raw_frames(
+        &mut self,
+        camera: Camera,
+    ) -> impl Stream<Item = Result<CameraFrame, KachakaApiError>> {
+        api_impl::watch_camera_raw_frames(&mut self.client, camera).await
+    }
+
     // GetRobotErrorCodeJson
     pub async fn get_robot_error_code_json(
         &mut self,
@@ -195,6 +387,20 @@ impl KachakaApiClient {
         api_impl::watch_error(&mut self.client).await
     }
 
+    pub async fn get_error_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<Vec<KachakaError>>, KachakaApiError> {
+        api_impl::get_error_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_error_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<Vec<KachakaError>>, KachakaApiError>> {
+        api_impl::watch_error_from(&mut self.client, cursor).await
+    }
+
     // GetCommandState
     pub async fn get_command_state(
         &mut self,
@@ -213,6 +419,20 @@ impl KachakaApiClient {
         api_impl::watch_command_state(&mut self.client).await
     }
 
+    pub async fn get_command_state_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<CommandState>, KachakaApiError> {
+        api_impl::get_command_state_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_command_state_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<CommandState>, KachakaApiError>> {
+        api_impl::watch_command_state_from(&mut self.client, cursor).await
+    }
+
     // GetLastCommandResult
     pub async fn get_last_command_result(
         &mut self,
@@ -227,7 +447,29 @@ impl KachakaApiClient {
         api_impl::watch_last_command_result(&mut self.client).await
     }
 
+    pub async fn get_last_command_result_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<Option<CommandResult>>, KachakaApiError> {
+        api_impl::get_last_command_result_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_last_command_result_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<Option<CommandResult>>, KachakaApiError>> {
+        api_impl::watch_last_command_result_from(&mut self.client, cursor).await
+    }
+
     // command api
+    pub async fn start_command(
+        &mut self,
+        command: Command,
+        options: StartCommandOptions,
+    ) -> Result<String, KachakaApiError> {
+        api_impl::start_command(&mut self.client, command, options).await
+    }
+
     pub async fn move_shelf(
         &mut self,
         shelf_id: &str,
@@ -284,12 +526,10 @@ impl KachakaApiClient {
 
     pub async fn move_to_pose(
         &mut self,
-        x: f64,
-        y: f64,
-        yaw: f64,
+        pose: &Pose,
         options: StartCommandOptions,
     ) -> Result<String, KachakaApiError> {
-        api_impl::move_to_pose(&mut self.client, x, y, yaw, options).await
+        api_impl::move_to_pose(&mut self.client, pose, options).await
     }
 
     pub async fn lock(
@@ -333,6 +573,36 @@ impl KachakaApiClient {
         api_impl::proceed(&mut self.client).await
     }
 
+    // map
+    // GetPngMap
+    pub async fn get_png_map(&mut self, cursor: i64) -> Result<kachaka_api::Map, KachakaApiError> {
+        api_impl::get_png_map(&mut self.client, cursor).await
+    }
+
+    pub async fn get_latest_png_map(&mut self) -> Result<kachaka_api::Map, KachakaApiError> {
+        api_impl::get_latest_png_map(&mut self.client).await
+    }
+
+    pub async fn watch_png_map(
+        &mut self,
+    ) -> impl Stream<Item = Result<kachaka_api::Map, KachakaApiError>> {
+        api_impl::watch_png_map(&mut self.client).await
+    }
+
+    pub async fn get_png_map_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<kachaka_api::Map>, KachakaApiError> {
+        api_impl::get_png_map_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_png_map_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<kachaka_api::Map>, KachakaApiError>> {
+        api_impl::watch_png_map_from(&mut self.client, cursor).await
+    }
+
     // locations
     // GetLocations
     pub async fn get_locations(
@@ -354,6 +624,20 @@ impl KachakaApiClient {
         api_impl::watch_locations(&mut self.client).await
     }
 
+    pub async fn get_locations_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<Vec<kachaka_api::Location>>, KachakaApiError> {
+        api_impl::get_locations_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_locations_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<Vec<kachaka_api::Location>>, KachakaApiError>> {
+        api_impl::watch_locations_from(&mut self.client, cursor).await
+    }
+
     // shelves
     // GetShelves
     pub async fn get_shelves(
@@ -373,6 +657,20 @@ impl KachakaApiClient {
         api_impl::watch_shelves(&mut self.client).await
     }
 
+    pub async fn get_shelves_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<Vec<kachaka_api::Shelf>>, KachakaApiError> {
+        api_impl::get_shelves_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_shelves_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<Vec<kachaka_api::Shelf>>, KachakaApiError>> {
+        api_impl::watch_shelves_from(&mut self.client, cursor).await
+    }
+
     // GetMovingShelfId
     pub async fn get_moving_shelf_id(&mut self, cursor: i64) -> Result<String, KachakaApiError> {
         api_impl::get_moving_shelf_id(&mut self.client, cursor).await
@@ -388,6 +686,20 @@ impl KachakaApiClient {
         api_impl::watch_moving_shelf_id(&mut self.client).await
     }
 
+    pub async fn get_moving_shelf_id_versioned(
+        &mut self,
+        cursor: i64,
+    ) -> Result<Versioned<String>, KachakaApiError> {
+        api_impl::get_moving_shelf_id_versioned(&mut self.client, cursor).await
+    }
+
+    pub async fn watch_moving_shelf_id_from(
+        &mut self,
+        cursor: i64,
+    ) -> impl Stream<Item = Result<Versioned<String>, KachakaApiError>> {
+        api_impl::watch_moving_shelf_id_from(&mut self.client, cursor).await
+    }
+
     // ResetShelfPose
     pub async fn reset_shelf_pose(&mut self, shelf_id: &str) -> Result<(), KachakaApiError> {
         api_impl::reset_shelf_pose(&mut self.client, shelf_id).await
@@ -0,0 +1,205 @@
+use futures::stream::{Stream, StreamExt};
+use std::pin::Pin;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use crate::types::{Command, CommandResult, KachakaApiError};
+use crate::{KachakaApiClient, StartCommandOptions};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MissionStep {
+    pub command: Command,
+    pub options: StartCommandOptions,
+    pub max_retries: u32,
+}
+
+impl MissionStep {
+    pub fn new(command: Command) -> Self {
+        Self {
+            command,
+            options: StartCommandOptions::default(),
+            max_retries: 0,
+        }
+    }
+
+    pub fn options(mut self, options: StartCommandOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+}
+
+/// An ordered list of commands executed one after another.
+///
+/// Each step waits for the robot to report the result of its command before the
+/// next one is started. When a step fails after all of its retries, the
+/// `on_failure` steps are run (best effort, without retries) and the mission stops.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mission {
+    pub steps: Vec<MissionStep>,
+    pub on_failure: Vec<MissionStep>,
+}
+
+#[derive(Debug)]
+pub enum MissionEvent {
+    StepStarted {
+        index: usize,
+        attempt: u32,
+        command_id: String,
+    },
+    StepSucceeded {
+        index: usize,
+    },
+    StepFailed {
+        index: usize,
+        attempt: u32,
+        error: KachakaApiError,
+    },
+    OnFailureStepStarted {
+        index: usize,
+        command_id: String,
+    },
+    OnFailureStepFinished {
+        index: usize,
+        result: Result<(), KachakaApiError>,
+    },
+    Completed,
+    Aborted {
+        index: usize,
+    },
+}
+
+impl Mission {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn step(mut self, step: MissionStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn on_failure(mut self, step: MissionStep) -> Self {
+        self.on_failure.push(step);
+        self
+    }
+
+    /// Runs the mission in a background task and reports its progress.
+    ///
+    /// The stream ends after `MissionEvent::Completed` or `MissionEvent::Aborted`.
+    pub async fn run(
+        self,
+        kachaka_api_client: KachakaApiClient,
+    ) -> impl Stream<Item = MissionEvent> {
+        let (tx, rx) = mpsc::unbounded_channel::<MissionEvent>();
+        tokio::spawn(async move {
+            let mut runner = MissionRunner::new(kachaka_api_client, tx).await;
+            runner.run(self).await;
+        });
+        UnboundedReceiverStream::new(rx)
+    }
+}
+
+type CommandResultStream =
+    Pin<Box<dyn Stream<Item = Result<Option<CommandResult>, KachakaApiError>> + Send>>;
+
+struct MissionRunner {
+    kachaka_api_client: KachakaApiClient,
+    last_command_result_stream: CommandResultStream,
+    tx: mpsc::UnboundedSender<MissionEvent>,
+}
+
+impl MissionRunner {
+    async fn new(
+        mut kachaka_api_client: KachakaApiClient,
+        tx: mpsc::UnboundedSender<MissionEvent>,
+    ) -> Self {
+        let mut last_command_result_stream: CommandResultStream =
+            Box::pin(kachaka_api_client.watch_last_command_result().await);
+        // The first item is the result of whatever ran before the mission.
+        last_command_result_stream.next().await;
+        Self {
+            kachaka_api_client,
+            last_command_result_stream,
+            tx,
+        }
+    }
+
+    async fn run(&mut self, mission: Mission) {
+        for (index, step) in mission.steps.iter().enumerate() {
+            if !self.run_step_with_retries(index, step).await {
+                for (index, step) in mission.on_failure.iter().enumerate() {
+                    let result = self
+                        .execute(step, |command_id| MissionEvent::OnFailureStepStarted {
+                            index,
+                            command_id,
+                        })
+                        .await;
+                    let _ = self
+                        .tx
+                        .send(MissionEvent::OnFailureStepFinished { index, result });
+                }
+                let _ = self.tx.send(MissionEvent::Aborted { index });
+                return;
+            }
+        }
+        let _ = self.tx.send(MissionEvent::Completed);
+    }
+
+    async fn run_step_with_retries(&mut self, index: usize, step: &MissionStep) -> bool {
+        for attempt in 0..=step.max_retries {
+            let result = self
+                .execute(step, |command_id| MissionEvent::StepStarted {
+                    index,
+                    attempt,
+                    command_id,
+                })
+                .await;
+            match result {
+                Ok(()) => {
+                    let _ = self.tx.send(MissionEvent::StepSucceeded { index });
+                    return true;
+                }
+                Err(error) => {
+                    let _ = self.tx.send(MissionEvent::StepFailed {
+                        index,
+                        attempt,
+                        error,
+                    });
+                }
+            }
+        }
+        false
+    }
+
+    async fn execute(
+        &mut self,
+        step: &MissionStep,
+        started_event: impl FnOnce(String) -> MissionEvent,
+    ) -> Result<(), KachakaApiError> {
+        let command_id = self
+            .kachaka_api_client
+            .start_command(step.command.clone(), step.options.clone())
+            .await?;
+        let _ = self.tx.send(started_event(command_id));
+        self.wait_for_result(&step.command).await
+    }
+
+    async fn wait_for_result(&mut self, command: &Command) -> Result<(), KachakaApiError> {
+        while let Some(last_command_result) = self.last_command_result_stream.next().await {
+            match last_command_result? {
+                Some(result) if &result.command == command => {
+                    return result.result.map_err(KachakaApiError::ApiError);
+                }
+                _ => continue,
+            }
+        }
+        Err(KachakaApiError::NullResult)
+    }
+}
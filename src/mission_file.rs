@@ -0,0 +1,304 @@
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::kachaka_api;
+use crate::mission::{Mission, MissionStep};
+use crate::shelf_location_resolver::{LayoutLookupError, ShelfLocationResolver};
+use crate::types::Command;
+use crate::StartCommandOptions;
+
+#[derive(Debug)]
+pub enum MissionFileError {
+    IoError(std::io::Error),
+    YamlParseError(serde_yaml::Error),
+    TomlParseError(toml::de::Error),
+    UnsupportedExtension(String),
+    ValidationError(Vec<MissionFileValidationError>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissionFileValidationError {
+    ShelfNotFound {
+        step: String,
+        shelf: String,
+    },
+    LocationNotFound {
+        step: String,
+        location: String,
+    },
+    AmbiguousShelf {
+        step: String,
+        shelf: String,
+        ids: Vec<String>,
+    },
+    AmbiguousLocation {
+        step: String,
+        location: String,
+        ids: Vec<String>,
+    },
+}
+
+/// A mission description loaded from a YAML or TOML file.
+///
+/// Shelves and locations are referenced by name (or id) and resolved against the
+/// current layout by [`MissionFile::resolve`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct MissionFile {
+    #[serde(default)]
+    pub steps: Vec<MissionFileStep>,
+    #[serde(default)]
+    pub on_failure: Vec<MissionFileStep>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MissionFileStep {
+    #[serde(flatten)]
+    pub command: MissionFileCommand,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub tts_on_success: String,
+    #[serde(default)]
+    pub cancel_all: bool,
+    #[serde(default)]
+    pub deferrable: bool,
+    pub lock_on_end: Option<f64>,
+    #[serde(default)]
+    pub max_retries: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum MissionFileCommand {
+    MoveShelf {
+        shelf: String,
+        location: String,
+    },
+    ReturnShelf {
+        #[serde(default)]
+        shelf: String,
+    },
+    UndockShelf,
+    MoveToLocation {
+        location: String,
+    },
+    ReturnHome,
+    DockShelf,
+    Speak {
+        text: String,
+    },
+    MoveToPose {
+        x: f64,
+        y: f64,
+        yaw: f64,
+    },
+    Lock {
+        duration_sec: f64,
+    },
+    MoveForward {
+        distance_meter: f64,
+        speed: f64,
+    },
+    RotateInPlace {
+        angle_radian: f64,
+    },
+    DockAnyShelfWithRegistration {
+        location: String,
+    },
+}
+
+impl MissionFile {
+    pub fn from_yaml_str(s: &str) -> Result<Self, MissionFileError> {
+        serde_yaml::from_str(s).map_err(MissionFileError::YamlParseError)
+    }
+
+    pub fn from_toml_str(s: &str) -> Result<Self, MissionFileError> {
+        toml::from_str(s).map_err(MissionFileError::TomlParseError)
+    }
+
+    /// Loads a mission file, choosing the format from the extension
+    /// (`.yaml`, `.yml` or `.toml`).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MissionFileError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(MissionFileError::IoError)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => Self::from_yaml_str(&content),
+            Some("toml") => Self::from_toml_str(&content),
+            extension => Err(MissionFileError::UnsupportedExtension(
+                extension.unwrap_or_default().to_string(),
+            )),
+        }
+    }
+
+    /// Resolves every shelf and location name against the current layout and
+    /// builds a runnable [`Mission`].
+    ///
+    /// All unresolved references are reported at once, and nothing is sent to the
+    /// robot unless the whole file is valid.
+    pub async fn resolve(
+        &self,
+        resolver: &ShelfLocationResolver,
+    ) -> Result<Mission, MissionFileError> {
+        let mut errors = Vec::new();
+        let mut mission = Mission::new();
+        for (index, step) in self.steps.iter().enumerate() {
+            let step_name = format!("steps[{}]", index);
+            if let Some(step) = step.resolve(&step_name, resolver, &mut errors).await {
+                mission = mission.step(step);
+            }
+        }
+        for (index, step) in self.on_failure.iter().enumerate() {
+            let step_name = format!("on_failure[{}]", index);
+            if let Some(step) = step.resolve(&step_name, resolver, &mut errors).await {
+                mission = mission.on_failure(step);
+            }
+        }
+        if errors.is_empty() {
+            Ok(mission)
+        } else {
+            Err(MissionFileError::ValidationError(errors))
+        }
+    }
+}
+
+impl MissionFileStep {
+    async fn resolve(
+        &self,
+        step_name: &str,
+        resolver: &ShelfLocationResolver,
+        errors: &mut Vec<MissionFileValidationError>,
+    ) -> Option<MissionStep> {
+        let command = self.command.resolve(step_name, resolver, errors).await?;
+        let options = StartCommandOptions::default()
+            .title(&self.title)
+            .tts_on_success(&self.tts_on_success)
+            .cancel_all(self.cancel_all)
+            .deferrable(self.deferrable)
+            .lock_on_end(
+                self.lock_on_end
+                    .map(|duration_sec| kachaka_api::LockOnEnd { duration_sec }),
+            );
+        Some(
+            MissionStep::new(command)
+                .options(options)
+                .max_retries(self.max_retries),
+        )
+    }
+}
+
+impl MissionFileCommand {
+    async fn resolve(
+        &self,
+        step_name: &str,
+        resolver: &ShelfLocationResolver,
+        errors: &mut Vec<MissionFileValidationError>,
+    ) -> Option<Command> {
+        let command = match self {
+            MissionFileCommand::MoveShelf { shelf, location } => {
+                let shelf_id = resolve_shelf(step_name, shelf, resolver, errors).await;
+                let location_id = resolve_location(step_name, location, resolver, errors).await;
+                Command::MoveShelf {
+                    shelf_id: shelf_id?,
+                    location_id: location_id?,
+                }
+            }
+            MissionFileCommand::ReturnShelf { shelf } => Command::ReturnShelf {
+                shelf_id: if shelf.is_empty() {
+                    String::new()
+                } else {
+                    resolve_shelf(step_name, shelf, resolver, errors).await?
+                },
+            },
+            MissionFileCommand::UndockShelf => Command::UndockShelf {
+                shelf_id: String::new(),
+            },
+            MissionFileCommand::MoveToLocation { location } => Command::MoveToLocation {
+                location_id: resolve_location(step_name, location, resolver, errors).await?,
+            },
+            MissionFileCommand::ReturnHome => Command::ReturnHome,
+            MissionFileCommand::DockShelf => Command::DockShelf,
+            MissionFileCommand::Speak { text } => Command::Speak { text: text.clone() },
+            MissionFileCommand::MoveToPose { x, y, yaw } => Command::MoveToPose {
+                x: *x,
+                y: *y,
+                yaw: *yaw,
+            },
+            MissionFileCommand::Lock { duration_sec } => Command::Lock {
+                duration_sec: *duration_sec,
+            },
+            MissionFileCommand::MoveForward {
+                distance_meter,
+                speed,
+            } => Command::MoveForward {
+                distance_meter: *distance_meter,
+                speed: *speed,
+            },
+            MissionFileCommand::RotateInPlace { angle_radian } => Command::RotateInPlace {
+                angle_radian: *angle_radian,
+            },
+            MissionFileCommand::DockAnyShelfWithRegistration { location } => {
+                Command::DockAnyShelfWithRegistration {
+                    location_id: resolve_location(step_name, location, resolver, errors).await?,
+                    dock_forward: true,
+                }
+            }
+        };
+        Some(command)
+    }
+}
+
+async fn resolve_shelf(
+    step_name: &str,
+    shelf: &str,
+    resolver: &ShelfLocationResolver,
+    errors: &mut Vec<MissionFileValidationError>,
+) -> Option<String> {
+    match resolver.resolve_shelf(shelf).await {
+        Ok(found) => Some(found.id),
+        Err(error) => {
+            errors.push(match error {
+                LayoutLookupError::NotFound { .. } => MissionFileValidationError::ShelfNotFound {
+                    step: step_name.to_string(),
+                    shelf: shelf.to_string(),
+                },
+                LayoutLookupError::Ambiguous { ids, .. } => {
+                    MissionFileValidationError::AmbiguousShelf {
+                        step: step_name.to_string(),
+                        shelf: shelf.to_string(),
+                        ids,
+                    }
+                }
+            });
+            None
+        }
+    }
+}
+
+async fn resolve_location(
+    step_name: &str,
+    location: &str,
+    resolver: &ShelfLocationResolver,
+    errors: &mut Vec<MissionFileValidationError>,
+) -> Option<String> {
+    match resolver.resolve_location(location).await {
+        Ok(found) => Some(found.id),
+        Err(error) => {
+            errors.push(match error {
+                LayoutLookupError::NotFound { .. } => {
+                    MissionFileValidationError::LocationNotFound {
+                        step: step_name.to_string(),
+                        location: location.to_string(),
+                    }
+                }
+                LayoutLookupError::Ambiguous { ids, .. } => {
+                    MissionFileValidationError::AmbiguousLocation {
+                        step: step_name.to_string(),
+                        location: location.to_string(),
+                        ids,
+                    }
+                }
+            });
+            None
+        }
+    }
+}
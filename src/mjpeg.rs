@@ -0,0 +1,180 @@
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::header;
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use futures::stream::{Stream, StreamExt};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::ToSocketAddrs;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::WatchStream;
+
+use crate::api_impl;
+use crate::types::Camera;
+use crate::KachakaApiClient;
+
+const BOUNDARY: &str = "frame";
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(1);
+
+struct BroadcasterInner {
+    client: KachakaApiClient,
+    camera: Camera,
+    latest: watch::Sender<Option<Bytes>>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Shares one subscription to the compressed images of a camera between any number
+/// of viewers.
+///
+/// The robot is only polled while someone is watching; the subscription ends with
+/// the last viewer. Every viewer gets the latest frame when it is ready for one, so
+/// slow viewers skip frames instead of delaying the others.
+#[derive(Clone)]
+pub struct CameraBroadcaster {
+    inner: Arc<BroadcasterInner>,
+}
+
+impl CameraBroadcaster {
+    pub fn new(client: KachakaApiClient, camera: Camera) -> Self {
+        Self {
+            inner: Arc::new(BroadcasterInner {
+                client,
+                camera,
+                latest: watch::channel(None).0,
+                task: Mutex::new(None),
+            }),
+        }
+    }
+
+    pub fn camera(&self) -> Camera {
+        self.inner.camera
+    }
+
+    /// JPEG frames, starting with the last one received, if any.
+    pub fn subscribe(&self) -> impl Stream<Item = Bytes> {
+        let mut task = self.inner.task.lock().unwrap();
+        let receiver = self.inner.latest.subscribe();
+        if task.is_none() {
+            *task = Some(tokio::spawn(run_subscription(self.inner.clone())));
+        }
+        WatchStream::new(receiver).filter_map(futures::future::ready)
+    }
+
+    pub fn viewer_count(&self) -> usize {
+        self.inner.latest.receiver_count()
+    }
+}
+
+async fn run_subscription(inner: Arc<BroadcasterInner>) {
+    loop {
+        let mut images = Box::pin(api_impl::watch_camera_ros_compressed_image_raw(
+            &inner.client.client,
+            inner.camera,
+        ));
+        loop {
+            tokio::select! {
+                _ = inner.latest.closed() => {
+                    // `subscribe` adds viewers under the same lock.
+                    let mut task = inner.task.lock().unwrap();
+                    if inner.latest.receiver_count() == 0 {
+                        *task = None;
+                        return;
+                    }
+                }
+                item = images.next() => match item {
+                    Some(Ok(image)) => {
+                        inner.latest.send_replace(Some(Bytes::from(image.data)));
+                    }
+                    _ => break,
+                },
+            }
+        }
+        tokio::time::sleep(RESUBSCRIBE_INTERVAL).await;
+    }
+}
+
+/// A `multipart/x-mixed-replace` response that shows the frames as a live video in
+/// browsers and most NVRs.
+pub fn mjpeg_response(frames: impl Stream<Item = Bytes> + Send + 'static) -> Response {
+    let parts = frames.flat_map(|jpeg| {
+        let part_header = format!(
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            BOUNDARY,
+            jpeg.len()
+        );
+        futures::stream::iter([
+            Ok::<_, Infallible>(Bytes::from(part_header)),
+            Ok(jpeg),
+            Ok(Bytes::from_static(b"\r\n")),
+        ])
+    });
+    (
+        [
+            (
+                header::CONTENT_TYPE,
+                format!("multipart/x-mixed-replace; boundary={}", BOUNDARY),
+            ),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+        ],
+        Body::from_stream(parts),
+    )
+        .into_response()
+}
+
+/// Serves both cameras of a robot at `/front.mjpeg` and `/back.mjpeg`, with a
+/// page showing both at `/`.
+#[derive(Clone)]
+pub struct MjpegServer {
+    front: CameraBroadcaster,
+    back: CameraBroadcaster,
+}
+
+impl MjpegServer {
+    pub fn new(client: KachakaApiClient) -> Self {
+        Self {
+            front: CameraBroadcaster::new(client.clone(), Camera::Front),
+            back: CameraBroadcaster::new(client, Camera::Back),
+        }
+    }
+
+    pub fn broadcaster(&self, camera: Camera) -> &CameraBroadcaster {
+        match camera {
+            Camera::Front => &self.front,
+            Camera::Back => &self.back,
+        }
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/", get(index))
+            .route("/front.mjpeg", get(stream_front))
+            .route("/back.mjpeg", get(stream_back))
+            .with_state(self.clone())
+    }
+
+    pub async fn serve(&self, address: impl ToSocketAddrs) -> std::io::Result<()> {
+        let listener = tokio::net::TcpListener::bind(address).await?;
+        axum::serve(listener, self.router()).await
+    }
+}
+
+async fn index() -> Html<&'static str> {
+    Html(
+        "<!DOCTYPE html><html><body>\
+         <img src=\"front.mjpeg\" alt=\"front camera\">\
+         <img src=\"back.mjpeg\" alt=\"back camera\">\
+         </body></html>",
+    )
+}
+
+async fn stream_front(State(server): State<MjpegServer>) -> Response {
+    mjpeg_response(server.front.subscribe())
+}
+
+async fn stream_back(State(server): State<MjpegServer>) -> Response {
+    mjpeg_response(server.back.subscribe())
+}
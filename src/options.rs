@@ -1,6 +1,12 @@
 use crate::kachaka_api;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct StartCommandOptions {
     pub title: String,
     pub tts_on_success: String,
@@ -0,0 +1,447 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::Duration;
+
+use crate::geometry::angle_difference;
+use crate::kachaka_api;
+use crate::types::Pose;
+
+/// Pixels darker than this are obstacles.
+pub const OCCUPIED_THRESHOLD: u8 = 100;
+/// Pixels brighter than this are free space; the rest is unknown.
+pub const FREE_THRESHOLD: u8 = 230;
+/// Approximate circumscribed radius of the robot body in meters.
+pub const ROBOT_RADIUS: f64 = 0.23;
+
+#[derive(Debug)]
+pub enum PathPlanningError {
+    MapDecodeError(image::ImageError),
+    InvalidMap,
+    StartOutOfMap,
+    GoalOutOfMap,
+    StartBlocked,
+    GoalBlocked,
+    Unreachable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Free,
+    Occupied,
+    Unknown,
+}
+
+/// A grid map in which row 0 is the top of the image, as in the PNG map; `origin` is
+/// the pose of the bottom-left corner of the map.
+#[derive(Debug, Clone)]
+pub struct OccupancyGrid {
+    pub width: usize,
+    pub height: usize,
+    /// Meters per cell.
+    pub resolution: f64,
+    pub origin: Pose,
+    cells: Vec<Cell>,
+}
+
+impl OccupancyGrid {
+    pub fn new(
+        width: usize,
+        height: usize,
+        resolution: f64,
+        origin: Pose,
+        cells: Vec<Cell>,
+    ) -> Result<Self, PathPlanningError> {
+        if cells.len() != width * height || resolution <= 0.0 {
+            return Err(PathPlanningError::InvalidMap);
+        }
+        Ok(Self {
+            width,
+            height,
+            resolution,
+            origin,
+            cells,
+        })
+    }
+
+    /// Decodes the map returned by `get_png_map`, classifying pixels with
+    /// [`OCCUPIED_THRESHOLD`] and [`FREE_THRESHOLD`].
+    pub fn from_map(map: &kachaka_api::Map) -> Result<Self, PathPlanningError> {
+        let image = image::load_from_memory(&map.data)
+            .map_err(PathPlanningError::MapDecodeError)?
+            .into_luma8();
+        let cells = image
+            .pixels()
+            .map(|pixel| match pixel.0[0] {
+                luma if luma < OCCUPIED_THRESHOLD => Cell::Occupied,
+                luma if luma > FREE_THRESHOLD => Cell::Free,
+                _ => Cell::Unknown,
+            })
+            .collect();
+        Self::new(
+            image.width() as usize,
+            image.height() as usize,
+            map.resolution,
+            map.origin
+                .as_ref()
+                .map(Pose::from)
+                .unwrap_or(Pose::identity()),
+            cells,
+        )
+    }
+
+    pub fn cell(&self, col: usize, row: usize) -> Option<Cell> {
+        (col < self.width && row < self.height).then(|| self.cells[row * self.width + col])
+    }
+
+    /// The cell containing a point of the map frame, `None` outside the map.
+    pub fn world_to_cell(&self, x: f64, y: f64) -> Option<(usize, usize)> {
+        let local = Pose::new(x, y, 0.0).relative_to(&self.origin);
+        let col = (local.x / self.resolution).floor();
+        let row_from_bottom = (local.y / self.resolution).floor();
+        if col < 0.0
+            || row_from_bottom < 0.0
+            || col >= self.width as f64
+            || row_from_bottom >= self.height as f64
+        {
+            return None;
+        }
+        Some((col as usize, self.height - 1 - row_from_bottom as usize))
+    }
+
+    /// The center of a cell in the map frame.
+    pub fn cell_to_world(&self, col: usize, row: usize) -> (f64, f64) {
+        self.origin.transform_point(
+            (col as f64 + 0.5) * self.resolution,
+            ((self.height - 1 - row) as f64 + 0.5) * self.resolution,
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannerConfig {
+    /// Obstacles are inflated by this radius in meters.
+    pub footprint_radius: f64,
+    /// Whether unknown cells may be crossed.
+    pub allow_unknown: bool,
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        Self {
+            footprint_radius: ROBOT_RADIUS,
+            allow_unknown: false,
+        }
+    }
+}
+
+impl PlannerConfig {
+    pub fn footprint_radius(mut self, footprint_radius: f64) -> Self {
+        self.footprint_radius = footprint_radius;
+        self
+    }
+
+    /// Grows the footprint to cover a shelf carried by the robot.
+    pub fn shelf(mut self, size: &kachaka_api::ShelfSize) -> Self {
+        let shelf_radius = (size.width / 2.0).hypot(size.depth / 2.0);
+        self.footprint_radius = self.footprint_radius.max(shelf_radius);
+        self
+    }
+
+    pub fn allow_unknown(mut self, allow_unknown: bool) -> Self {
+        self.allow_unknown = allow_unknown;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedPath {
+    /// Waypoints from the start to the goal, both with their own orientation; each
+    /// intermediate waypoint faces the next.
+    pub poses: Vec<Pose>,
+    /// Length of the path in meters.
+    pub length: f64,
+}
+
+impl PlannedPath {
+    /// Travel time when driving each segment straight at `linear_speed` (m/s) and
+    /// turning in place at `angular_speed` (rad/s) between segments.
+    pub fn estimated_duration(&self, linear_speed: f64, angular_speed: f64) -> Duration {
+        let mut heading = self.poses.first().map_or(0.0, |pose| pose.theta);
+        let mut turning = 0.0;
+        for pair in self.poses.windows(2) {
+            if pair[0].distance(&pair[1]) > f64::EPSILON {
+                let segment = (pair[1].y - pair[0].y).atan2(pair[1].x - pair[0].x);
+                turning += angle_difference(segment, heading).abs();
+                heading = segment;
+            }
+        }
+        if let Some(goal) = self.poses.last() {
+            turning += angle_difference(goal.theta, heading).abs();
+        }
+        Duration::from_secs_f64(self.length / linear_speed + turning / angular_speed)
+    }
+}
+
+/// Any-angle (Theta*) planner on an [`OccupancyGrid`] with obstacles inflated by the
+/// footprint.
+///
+/// Inflation is ignored within the footprint radius of the start and the goal, so a
+/// robot standing next to a wall, e.g. on its charger, can still leave it.
+#[derive(Debug, Clone)]
+pub struct PathPlanner {
+    grid: OccupancyGrid,
+    config: PlannerConfig,
+    inflated: Vec<bool>,
+}
+
+impl PathPlanner {
+    pub fn new(grid: OccupancyGrid, config: PlannerConfig) -> Self {
+        let inflated = inflate(&grid, &config);
+        Self {
+            grid,
+            config,
+            inflated,
+        }
+    }
+
+    pub fn grid(&self) -> &OccupancyGrid {
+        &self.grid
+    }
+
+    pub fn plan(&self, start: &Pose, goal: &Pose) -> Result<PlannedPath, PathPlanningError> {
+        let start_cell = self
+            .grid
+            .world_to_cell(start.x, start.y)
+            .ok_or(PathPlanningError::StartOutOfMap)?;
+        let goal_cell = self
+            .grid
+            .world_to_cell(goal.x, goal.y)
+            .ok_or(PathPlanningError::GoalOutOfMap)?;
+        let search = Search {
+            planner: self,
+            bubble_cells: self.config.footprint_radius / self.grid.resolution,
+            start: start_cell,
+            goal: goal_cell,
+        };
+        if search.blocked(start_cell.0 as i64, start_cell.1 as i64) {
+            return Err(PathPlanningError::StartBlocked);
+        }
+        if search.blocked(goal_cell.0 as i64, goal_cell.1 as i64) {
+            return Err(PathPlanningError::GoalBlocked);
+        }
+        let cells = search.run().ok_or(PathPlanningError::Unreachable)?;
+
+        let mut points: Vec<(f64, f64)> = cells
+            .iter()
+            .map(|&(col, row)| self.grid.cell_to_world(col, row))
+            .collect();
+        points[0] = (start.x, start.y);
+        if points.len() == 1 {
+            points.push((goal.x, goal.y));
+        } else {
+            *points.last_mut().unwrap() = (goal.x, goal.y);
+        }
+        let mut poses: Vec<Pose> = points
+            .windows(2)
+            .map(|pair| {
+                let heading = (pair[1].1 - pair[0].1).atan2(pair[1].0 - pair[0].0);
+                Pose::new(pair[0].0, pair[0].1, heading)
+            })
+            .collect();
+        poses[0].theta = start.theta;
+        poses.push(goal.clone());
+        let length = poses
+            .windows(2)
+            .map(|pair| pair[0].distance(&pair[1]))
+            .sum();
+        Ok(PlannedPath { poses, length })
+    }
+
+    pub fn is_reachable(&self, start: &Pose, goal: &Pose) -> bool {
+        self.plan(start, goal).is_ok()
+    }
+}
+
+fn inflate(grid: &OccupancyGrid, config: &PlannerConfig) -> Vec<bool> {
+    let radius = (config.footprint_radius / grid.resolution).ceil() as i64;
+    let mut inflated = vec![false; grid.cells.len()];
+    for row in 0..grid.height as i64 {
+        for col in 0..grid.width as i64 {
+            let cell = grid.cells[row as usize * grid.width + col as usize];
+            if cell == Cell::Free || (cell == Cell::Unknown && config.allow_unknown) {
+                continue;
+            }
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let (x, y) = (col + dx, row + dy);
+                    if dx * dx + dy * dy <= radius * radius
+                        && (0..grid.width as i64).contains(&x)
+                        && (0..grid.height as i64).contains(&y)
+                    {
+                        inflated[y as usize * grid.width + x as usize] = true;
+                    }
+                }
+            }
+        }
+    }
+    inflated
+}
+
+struct Search<'a> {
+    planner: &'a PathPlanner,
+    bubble_cells: f64,
+    start: (usize, usize),
+    goal: (usize, usize),
+}
+
+#[derive(PartialEq)]
+struct OpenNode {
+    f: f64,
+    index: usize,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    // reversed, so that `BinaryHeap` pops the lowest cost first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f.total_cmp(&self.f)
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Search<'_> {
+    fn blocked(&self, col: i64, row: i64) -> bool {
+        let grid = &self.planner.grid;
+        if col < 0 || row < 0 || col >= grid.width as i64 || row >= grid.height as i64 {
+            return true;
+        }
+        let index = row as usize * grid.width + col as usize;
+        match grid.cells[index] {
+            Cell::Occupied => return true,
+            Cell::Unknown if !self.planner.config.allow_unknown => return true,
+            _ => {}
+        }
+        self.planner.inflated[index]
+            && ![self.start, self.goal].iter().any(|&(x, y)| {
+                ((col - x as i64) as f64).hypot((row - y as i64) as f64) <= self.bubble_cells
+            })
+    }
+
+    /// Whether the segment between two cell centers crosses only free cells; passing
+    /// exactly through a corner needs both side cells to be free.
+    fn line_of_sight(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+        let (dx, dy) = (to.0 as i64 - x, to.1 as i64 - y);
+        let (nx, ny) = (dx.abs(), dy.abs());
+        let (sx, sy) = (dx.signum(), dy.signum());
+        let (mut ix, mut iy) = (0, 0);
+        while ix < nx || iy < ny {
+            let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+            match decision.cmp(&0) {
+                Ordering::Equal => {
+                    if self.blocked(x + sx, y) || self.blocked(x, y + sy) {
+                        return false;
+                    }
+                    x += sx;
+                    y += sy;
+                    ix += 1;
+                    iy += 1;
+                }
+                Ordering::Less => {
+                    x += sx;
+                    ix += 1;
+                }
+                Ordering::Greater => {
+                    y += sy;
+                    iy += 1;
+                }
+            }
+            if self.blocked(x, y) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn run(&self) -> Option<Vec<(usize, usize)>> {
+        let width = self.planner.grid.width;
+        let size = width * self.planner.grid.height;
+        let coords = |index: usize| (index % width, index / width);
+        let distance = |a: usize, b: usize| {
+            let ((ax, ay), (bx, by)) = (coords(a), coords(b));
+            (ax as f64 - bx as f64).hypot(ay as f64 - by as f64)
+        };
+        let start = self.start.1 * width + self.start.0;
+        let goal = self.goal.1 * width + self.goal.0;
+
+        let mut g = vec![f64::INFINITY; size];
+        let mut parent = vec![usize::MAX; size];
+        let mut closed = vec![false; size];
+        let mut open = BinaryHeap::new();
+        g[start] = 0.0;
+        parent[start] = start;
+        open.push(OpenNode {
+            f: distance(start, goal),
+            index: start,
+        });
+        while let Some(OpenNode { index, .. }) = open.pop() {
+            if index == goal {
+                let mut path = vec![coords(goal)];
+                let mut current = goal;
+                while current != start {
+                    current = parent[current];
+                    path.push(coords(current));
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if std::mem::replace(&mut closed[index], true) {
+                continue;
+            }
+            let (col, row) = coords(index);
+            for (dx, dy) in NEIGHBORS {
+                let (x, y) = (col as i64 + dx, row as i64 + dy);
+                if self.blocked(x, y) {
+                    continue;
+                }
+                let neighbor = y as usize * width + x as usize;
+                if closed[neighbor] || !self.line_of_sight(coords(index), coords(neighbor)) {
+                    continue;
+                }
+                // Theta*: connect to the grandparent directly when it is visible.
+                let via = if self.line_of_sight(coords(parent[index]), coords(neighbor)) {
+                    parent[index]
+                } else {
+                    index
+                };
+                let cost = g[via] + distance(via, neighbor);
+                if cost < g[neighbor] {
+                    g[neighbor] = cost;
+                    parent[neighbor] = via;
+                    open.push(OpenNode {
+                        f: cost + distance(neighbor, goal),
+                        index: neighbor,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+const NEIGHBORS: [(i64, i64); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
@@ -0,0 +1,505 @@
+use futures::stream::Stream;
+use image::DynamicImage;
+use prost::Message;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::transport::Channel;
+
+use crate::kachaka_api;
+use crate::kachaka_api::kachaka_api_client::KachakaApiClient as TonicKachakaApiClient;
+use crate::types::{BatteryInfo, CommandResult, CommandState, KachakaApiError, KachakaError, Pose};
+use crate::KachakaApiClient;
+
+const FILE_HEADER: &[u8; 8] = b"KCHKREC1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordTopic {
+    RobotPose = 1,
+    BatteryInfo = 2,
+    Error = 3,
+    CommandState = 4,
+    LastCommandResult = 5,
+    FrontCameraRosImage = 6,
+    FrontCameraRosCompressedImage = 7,
+    BackCameraRosImage = 8,
+    BackCameraRosCompressedImage = 9,
+    Locations = 10,
+    Shelves = 11,
+    MovingShelfId = 12,
+}
+
+impl RecordTopic {
+    pub const ALL: [RecordTopic; 12] = [
+        RecordTopic::RobotPose,
+        RecordTopic::BatteryInfo,
+        RecordTopic::Error,
+        RecordTopic::CommandState,
+        RecordTopic::LastCommandResult,
+        RecordTopic::FrontCameraRosImage,
+        RecordTopic::FrontCameraRosCompressedImage,
+        RecordTopic::BackCameraRosImage,
+        RecordTopic::BackCameraRosCompressedImage,
+        RecordTopic::Locations,
+        RecordTopic::Shelves,
+        RecordTopic::MovingShelfId,
+    ];
+
+    fn from_u32(value: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|topic| *topic as u32 == value)
+    }
+}
+
+/// One response of a getter RPC, stored as the encoded protobuf message.
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// Receive time in nanoseconds since the UNIX epoch.
+    pub timestamp_nsec: i64,
+    pub topic: RecordTopic,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct EncodedRecord {
+    #[prost(int64, tag = "1")]
+    timestamp_nsec: i64,
+    #[prost(uint32, tag = "2")]
+    topic: u32,
+    #[prost(bytes = "vec", tag = "3")]
+    payload: Vec<u8>,
+}
+
+/// Writes the responses of the selected getters to a file until stopped.
+///
+/// The file is a short header followed by length-delimited protobuf records, each
+/// holding the receive time, the topic and the raw response message.
+pub struct Recorder {
+    topic_tasks: Vec<JoinHandle<()>>,
+    writer_task: JoinHandle<std::io::Result<()>>,
+}
+
+impl Recorder {
+    pub async fn start(
+        kachaka_api_client: &KachakaApiClient,
+        topics: &[RecordTopic],
+        path: impl AsRef<Path>,
+    ) -> std::io::Result<Self> {
+        let mut file = BufWriter::new(tokio::fs::File::create(path).await?);
+        file.write_all(FILE_HEADER).await?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<EncodedRecord>();
+        let writer_task = tokio::spawn(async move {
+            while let Some(record) = rx.recv().await {
+                file.write_all(&record.encode_length_delimited_to_vec())
+                    .await?;
+            }
+            file.flush().await
+        });
+
+        let client = &kachaka_api_client.client;
+        let topic_tasks = topics
+            .iter()
+            .map(|topic| match topic {
+                RecordTopic::RobotPose => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_robot_pose(request).await
+                    })
+                }
+                RecordTopic::BatteryInfo => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_battery_info(request).await
+                    })
+                }
+                RecordTopic::Error => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_error(request).await
+                    })
+                }
+                RecordTopic::CommandState => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_command_state(request).await
+                    })
+                }
+                RecordTopic::LastCommandResult => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_last_command_result(request).await
+                    })
+                }
+                RecordTopic::FrontCameraRosImage => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_front_camera_ros_image(request).await
+                    })
+                }
+                RecordTopic::FrontCameraRosCompressedImage => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_front_camera_ros_compressed_image(request).await
+                    })
+                }
+                RecordTopic::BackCameraRosImage => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_back_camera_ros_image(request).await
+                    })
+                }
+                RecordTopic::BackCameraRosCompressedImage => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_back_camera_ros_compressed_image(request).await
+                    })
+                }
+                RecordTopic::Locations => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_locations(request).await
+                    })
+                }
+                RecordTopic::Shelves => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_shelves(request).await
+                    })
+                }
+                RecordTopic::MovingShelfId => {
+                    spawn_topic(client, *topic, tx.clone(), |mut client, request| async move {
+                        client.get_moving_shelf_id(request).await
+                    })
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            topic_tasks,
+            writer_task,
+        })
+    }
+
+    /// Stops all subscriptions and flushes the file.
+    pub async fn stop(self) -> std::io::Result<()> {
+        for task in &self.topic_tasks {
+            task.abort();
+        }
+        for task in self.topic_tasks {
+            let _ = task.await;
+        }
+        self.writer_task.await.map_err(std::io::Error::other)?
+    }
+}
+
+trait HasMetadata {
+    fn metadata(&self) -> Option<kachaka_api::Metadata>;
+}
+
+macro_rules! impl_has_metadata {
+    ($($response:ty),* $(,)?) => {
+        $(
+            impl HasMetadata for $response {
+                fn metadata(&self) -> Option<kachaka_api::Metadata> {
+                    self.metadata
+                }
+            }
+        )*
+    };
+}
+
+impl_has_metadata!(
+    kachaka_api::GetRobotPoseResponse,
+    kachaka_api::GetBatteryInfoResponse,
+    kachaka_api::GetErrorResponse,
+    kachaka_api::GetCommandStateResponse,
+    kachaka_api::GetLastCommandResultResponse,
+    kachaka_api::GetRosImageResponse,
+    kachaka_api::GetRosCompressedImageResponse,
+    kachaka_api::GetLocationsResponse,
+    kachaka_api::GetShelvesResponse,
+    kachaka_api::GetMovingShelfIdResponse,
+);
+
+fn spawn_topic<R, F, Fut>(
+    client: &TonicKachakaApiClient<Channel>,
+    topic: RecordTopic,
+    tx: mpsc::UnboundedSender<EncodedRecord>,
+    call: F,
+) -> JoinHandle<()>
+where
+    R: Message + HasMetadata + 'static,
+    F: Fn(TonicKachakaApiClient<Channel>, tonic::Request<kachaka_api::GetRequest>) -> Fut
+        + Send
+        + 'static,
+    Fut: Future<Output = Result<tonic::Response<R>, tonic::Status>> + Send,
+{
+    let client = client.clone();
+    tokio::spawn(async move {
+        let mut cursor = 0;
+        loop {
+            let request = tonic::Request::new(kachaka_api::GetRequest {
+                metadata: Some(kachaka_api::Metadata { cursor }),
+            });
+            match call(client.clone(), request).await {
+                Ok(response) => {
+                    let response = response.into_inner();
+                    cursor = response
+                        .metadata()
+                        .map_or(cursor, |metadata| metadata.cursor);
+                    let record = EncodedRecord {
+                        timestamp_nsec: now_nsec(),
+                        topic: topic as u32,
+                        payload: response.encode_to_vec(),
+                    };
+                    if tx.send(record).is_err() {
+                        break;
+                    }
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(500)).await,
+            }
+        }
+    })
+}
+
+fn now_nsec() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as i64)
+}
+
+/// The records of a file written by [`Recorder`].
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    records: Vec<Record>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let data = std::fs::read(path)?;
+        let invalid_data = |message: &str| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+        };
+        let mut buf = data
+            .strip_prefix(FILE_HEADER)
+            .ok_or_else(|| invalid_data("not a kachaka recording"))?;
+        let mut records = Vec::new();
+        while !buf.is_empty() {
+            let record = EncodedRecord::decode_length_delimited(&mut buf)
+                .map_err(|_| invalid_data("corrupted record"))?;
+            // Records of topics added by newer versions are skipped.
+            if let Some(topic) = RecordTopic::from_u32(record.topic) {
+                records.push(Record {
+                    timestamp_nsec: record.timestamp_nsec,
+                    topic,
+                    payload: record.payload,
+                });
+            }
+        }
+        Ok(Self { records })
+    }
+
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    pub fn duration(&self) -> Duration {
+        match (self.records.first(), self.records.last()) {
+            (Some(first), Some(last)) => {
+                Duration::from_nanos((last.timestamp_nsec - first.timestamp_nsec).max(0) as u64)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Plays a [`Recording`] back with the original timing, divided by `speed`.
+///
+/// All streams share the start time of the replayer, so topics stay in sync with each
+/// other. Unlike the live `watch_*` streams, replayed streams end with the recording.
+#[derive(Clone)]
+pub struct Replayer {
+    recording: Arc<Recording>,
+    speed: f64,
+    start: Instant,
+}
+
+impl Replayer {
+    pub fn new(recording: Recording, speed: f64) -> Self {
+        Self {
+            recording: Arc::new(recording),
+            speed,
+            start: Instant::now(),
+        }
+    }
+
+    fn replay<T, F>(
+        &self,
+        topic: RecordTopic,
+        convert: F,
+    ) -> impl Stream<Item = Result<T, KachakaApiError>>
+    where
+        T: Send + 'static,
+        F: Fn(&[u8]) -> Option<T> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::unbounded_channel::<Result<T, KachakaApiError>>();
+        let recording = self.recording.clone();
+        let (speed, start) = (self.speed, self.start);
+        tokio::spawn(async move {
+            let Some(first) = recording.records.first() else {
+                return;
+            };
+            let origin = first.timestamp_nsec;
+            for record in recording
+                .records
+                .iter()
+                .filter(|record| record.topic == topic)
+            {
+                let offset = Duration::from_nanos((record.timestamp_nsec - origin).max(0) as u64);
+                tokio::time::sleep_until(start + offset.div_f64(speed)).await;
+                if let Some(value) = convert(&record.payload) {
+                    if tx.send(Ok(value)).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        UnboundedReceiverStream::new(rx)
+    }
+
+    pub fn watch_robot_pose(&self) -> impl Stream<Item = Result<Pose, KachakaApiError>> {
+        self.replay(RecordTopic::RobotPose, |payload| {
+            kachaka_api::GetRobotPoseResponse::decode(payload)
+                .ok()?
+                .pose
+                .map(Pose::from)
+        })
+    }
+
+    pub fn watch_battery_info(&self) -> impl Stream<Item = Result<BatteryInfo, KachakaApiError>> {
+        self.replay(RecordTopic::BatteryInfo, |payload| {
+            let response = kachaka_api::GetBatteryInfoResponse::decode(payload).ok()?;
+            Some(BatteryInfo {
+                power_supply_status: response.power_supply_status.into(),
+                remaining_percentage: response.remaining_percentage,
+            })
+        })
+    }
+
+    pub fn watch_error(&self) -> impl Stream<Item = Result<Vec<KachakaError>, KachakaApiError>> {
+        self.replay(RecordTopic::Error, |payload| {
+            let response = kachaka_api::GetErrorResponse::decode(payload).ok()?;
+            Some(
+                response
+                    .error_codes
+                    .into_iter()
+                    .map(|error_code| KachakaError { error_code })
+                    .collect(),
+            )
+        })
+    }
+
+    pub fn watch_command_state(&self) -> impl Stream<Item = Result<CommandState, KachakaApiError>> {
+        self.replay(RecordTopic::CommandState, |payload| {
+            kachaka_api::GetCommandStateResponse::decode(payload)
+                .ok()
+                .map(CommandState::from)
+        })
+    }
+
+    pub fn watch_last_command_result(
+        &self,
+    ) -> impl Stream<Item = Result<Option<CommandResult>, KachakaApiError>> {
+        self.replay(RecordTopic::LastCommandResult, |payload| {
+            kachaka_api::GetLastCommandResultResponse::decode(payload)
+                .ok()
+                .map(Option::<CommandResult>::from)
+        })
+    }
+
+    pub fn watch_front_camera_ros_image(
+        &self,
+    ) -> impl Stream<Item = Result<DynamicImage, KachakaApiError>> {
+        self.replay(RecordTopic::FrontCameraRosImage, |payload| {
+            kachaka_api::GetRosImageResponse::decode(payload)
+                .ok()?
+                .image
+                .map(DynamicImage::from)
+        })
+    }
+
+    pub fn watch_front_camera_ros_compressed_image(
+        &self,
+    ) -> impl Stream<Item = Result<DynamicImage, KachakaApiError>> {
+        self.replay(RecordTopic::FrontCameraRosCompressedImage, |payload| {
+            kachaka_api::GetRosCompressedImageResponse::decode(payload)
+                .ok()?
+                .image
+                .map(DynamicImage::from)
+        })
+    }
+
+    pub fn watch_back_camera_ros_image(
+        &self,
+    ) -> impl Stream<Item = Result<DynamicImage, KachakaApiError>> {
+        self.replay(RecordTopic::BackCameraRosImage, |payload| {
+            kachaka_api::GetRosImageResponse::decode(payload)
+                .ok()?
+                .image
+                .map(DynamicImage::from)
+        })
+    }
+
+    pub fn watch_back_camera_ros_compressed_image(
+        &self,
+    ) -> impl Stream<Item = Result<DynamicImage, KachakaApiError>> {
+        self.replay(RecordTopic::BackCameraRosCompressedImage, |payload| {
+            kachaka_api::GetRosCompressedImageResponse::decode(payload)
+                .ok()?
+                .image
+                .map(DynamicImage::from)
+        })
+    }
+
+    pub fn watch_locations(
+        &self,
+    ) -> impl Stream<Item = Result<Vec<kachaka_api::Location>, KachakaApiError>> {
+        self.replay(RecordTopic::Locations, |payload| {
+            kachaka_api::GetLocationsResponse::decode(payload)
+                .ok()
+                .map(|response| response.locations)
+        })
+    }
+
+    pub fn watch_shelves(
+        &self,
+    ) -> impl Stream<Item = Result<Vec<kachaka_api::Shelf>, KachakaApiError>> {
+        self.replay(RecordTopic::Shelves, |payload| {
+            kachaka_api::GetShelvesResponse::decode(payload)
+                .ok()
+                .map(|response| response.shelves)
+        })
+    }
+
+    pub fn watch_moving_shelf_id(&self) -> impl Stream<Item = Result<String, KachakaApiError>> {
+        self.replay(RecordTopic::MovingShelfId, |payload| {
+            kachaka_api::GetMovingShelfIdResponse::decode(payload)
+                .ok()
+                .map(|response| response.shelf_id)
+        })
+    }
+
+    /// Applies the recorded responses to `fake` over time, so that any client
+    /// connected to it sees the recorded robot.
+    #[cfg(feature = "testing")]
+    pub fn replay_into(&self, fake: crate::testing::FakeKachaka) -> JoinHandle<()> {
+        let recording = self.recording.clone();
+        let (speed, start) = (self.speed, self.start);
+        tokio::spawn(async move {
+            let Some(first) = recording.records.first() else {
+                return;
+            };
+            let origin = first.timestamp_nsec;
+            for record in recording.records.iter() {
+                let offset = Duration::from_nanos((record.timestamp_nsec - origin).max(0) as u64);
+                tokio::time::sleep_until(start + offset.div_f64(speed)).await;
+                fake.apply_record(record);
+            }
+        })
+    }
+}
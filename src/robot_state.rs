@@ -0,0 +1,277 @@
+use futures::stream::{Stream, StreamExt};
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::WatchStream;
+
+use crate::kachaka_api::{Location, Shelf};
+use crate::types::{
+    BatteryInfo, CommandResult, CommandState, KachakaApiError, KachakaError, Pose, Versioned,
+};
+use crate::KachakaApiClient;
+
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The latest value of every topic followed by [`RobotState`]; `None` until the
+/// robot has reported it.
+#[derive(Debug, Clone, Default)]
+pub struct RobotSnapshot {
+    pub pose: Option<Versioned<Pose>>,
+    pub battery_info: Option<Versioned<BatteryInfo>>,
+    pub errors: Option<Versioned<Vec<KachakaError>>>,
+    pub command_state: Option<Versioned<CommandState>>,
+    /// The inner `None` means no command has finished yet.
+    pub last_command_result: Option<Versioned<Option<CommandResult>>>,
+    pub moving_shelf_id: Option<Versioned<String>>,
+    pub locations: Option<Versioned<Vec<Location>>>,
+    pub shelves: Option<Versioned<Vec<Shelf>>>,
+}
+
+impl RobotSnapshot {
+    /// Whether every topic has been received at least once.
+    pub fn is_complete(&self) -> bool {
+        self.pose.is_some()
+            && self.battery_info.is_some()
+            && self.errors.is_some()
+            && self.command_state.is_some()
+            && self.last_command_result.is_some()
+            && self.moving_shelf_id.is_some()
+            && self.locations.is_some()
+            && self.shelves.is_some()
+    }
+}
+
+/// Caches the state of a robot, with one subscription per topic shared by every
+/// reader.
+///
+/// A subscription that fails is started again after a short delay. The tasks stop
+/// when the state is dropped; share it with an `Arc`.
+pub struct RobotState {
+    client: KachakaApiClient,
+    snapshot: watch::Receiver<RobotSnapshot>,
+    pose: watch::Receiver<Option<Versioned<Pose>>>,
+    battery_info: watch::Receiver<Option<Versioned<BatteryInfo>>>,
+    errors: watch::Receiver<Option<Versioned<Vec<KachakaError>>>>,
+    command_state: watch::Receiver<Option<Versioned<CommandState>>>,
+    last_command_result: watch::Receiver<Option<Versioned<Option<CommandResult>>>>,
+    moving_shelf_id: watch::Receiver<Option<Versioned<String>>>,
+    locations: watch::Receiver<Option<Versioned<Vec<Location>>>>,
+    shelves: watch::Receiver<Option<Versioned<Vec<Shelf>>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl RobotState {
+    pub fn spawn(client: KachakaApiClient) -> Self {
+        let (snapshot_tx, snapshot) = watch::channel(RobotSnapshot::default());
+        let mut tasks = Vec::new();
+        let pose = spawn_subscription(
+            &client,
+            &snapshot_tx,
+            &mut tasks,
+            |snapshot| &mut snapshot.pose,
+            |mut client| async move { client.watch_robot_pose_from(0).await },
+        );
+        let battery_info = spawn_subscription(
+            &client,
+            &snapshot_tx,
+            &mut tasks,
+            |snapshot| &mut snapshot.battery_info,
+            |mut client| async move { client.watch_battery_info_from(0).await },
+        );
+        let errors = spawn_subscription(
+            &client,
+            &snapshot_tx,
+            &mut tasks,
+            |snapshot| &mut snapshot.errors,
+            |mut client| async move { client.watch_error_from(0).await },
+        );
+        let command_state = spawn_subscription(
+            &client,
+            &snapshot_tx,
+            &mut tasks,
+            |snapshot| &mut snapshot.command_state,
+            |mut client| async move { client.watch_command_state_from(0).await },
+        );
+        let last_command_result = spawn_subscription(
+            &client,
+            &snapshot_tx,
+            &mut tasks,
+            |snapshot| &mut snapshot.last_command_result,
+            |mut client| async move { client.watch_last_command_result_from(0).await },
+        );
+        let moving_shelf_id = spawn_subscription(
+            &client,
+            &snapshot_tx,
+            &mut tasks,
+            |snapshot| &mut snapshot.moving_shelf_id,
+            |mut client| async move { client.watch_moving_shelf_id_from(0).await },
+        );
+        let locations = spawn_subscription(
+            &client,
+            &snapshot_tx,
+            &mut tasks,
+            |snapshot| &mut snapshot.locations,
+            |mut client| async move { client.watch_locations_from(0).await },
+        );
+        let shelves = spawn_subscription(
+            &client,
+            &snapshot_tx,
+            &mut tasks,
+            |snapshot| &mut snapshot.shelves,
+            |mut client| async move { client.watch_shelves_from(0).await },
+        );
+        Self {
+            client,
+            snapshot,
+            pose,
+            battery_info,
+            errors,
+            command_state,
+            last_command_result,
+            moving_shelf_id,
+            locations,
+            shelves,
+            tasks,
+        }
+    }
+
+    pub fn client(&self) -> KachakaApiClient {
+        self.client.clone()
+    }
+
+    /// All topics at one point in time.
+    pub fn snapshot(&self) -> RobotSnapshot {
+        self.snapshot.borrow().clone()
+    }
+
+    /// A new snapshot whenever any topic changes.
+    pub fn watch_snapshot(&self) -> impl Stream<Item = RobotSnapshot> {
+        WatchStream::new(self.snapshot.clone())
+    }
+
+    /// Waits until every topic has been received.
+    pub async fn wait_until_ready(&self) -> RobotSnapshot {
+        let mut snapshot = self.snapshot.clone();
+        let ready = snapshot.wait_for(RobotSnapshot::is_complete).await;
+        // the sender lives as long as the state
+        ready.map(|snapshot| snapshot.clone()).unwrap_or_default()
+    }
+
+    pub fn robot_pose(&self) -> Option<Pose> {
+        latest(&self.pose)
+    }
+
+    pub fn battery_info(&self) -> Option<BatteryInfo> {
+        latest(&self.battery_info)
+    }
+
+    pub fn errors(&self) -> Option<Vec<KachakaError>> {
+        latest(&self.errors)
+    }
+
+    pub fn command_state(&self) -> Option<CommandState> {
+        latest(&self.command_state)
+    }
+
+    pub fn last_command_result(&self) -> Option<CommandResult> {
+        latest(&self.last_command_result).flatten()
+    }
+
+    pub fn moving_shelf_id(&self) -> Option<String> {
+        latest(&self.moving_shelf_id)
+    }
+
+    pub fn locations(&self) -> Option<Vec<Location>> {
+        latest(&self.locations)
+    }
+
+    pub fn shelves(&self) -> Option<Vec<Shelf>> {
+        latest(&self.shelves)
+    }
+
+    pub fn watch_robot_pose(&self) -> impl Stream<Item = Versioned<Pose>> {
+        watch_known(&self.pose)
+    }
+
+    pub fn watch_battery_info(&self) -> impl Stream<Item = Versioned<BatteryInfo>> {
+        watch_known(&self.battery_info)
+    }
+
+    pub fn watch_errors(&self) -> impl Stream<Item = Versioned<Vec<KachakaError>>> {
+        watch_known(&self.errors)
+    }
+
+    pub fn watch_command_state(&self) -> impl Stream<Item = Versioned<CommandState>> {
+        watch_known(&self.command_state)
+    }
+
+    pub fn watch_last_command_result(
+        &self,
+    ) -> impl Stream<Item = Versioned<Option<CommandResult>>> {
+        watch_known(&self.last_command_result)
+    }
+
+    pub fn watch_moving_shelf_id(&self) -> impl Stream<Item = Versioned<String>> {
+        watch_known(&self.moving_shelf_id)
+    }
+
+    pub fn watch_locations(&self) -> impl Stream<Item = Versioned<Vec<Location>>> {
+        watch_known(&self.locations)
+    }
+
+    pub fn watch_shelves(&self) -> impl Stream<Item = Versioned<Vec<Shelf>>> {
+        watch_known(&self.shelves)
+    }
+}
+
+impl Drop for RobotState {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+fn latest<T: Clone>(receiver: &watch::Receiver<Option<Versioned<T>>>) -> Option<T> {
+    receiver
+        .borrow()
+        .as_ref()
+        .map(|versioned| versioned.value.clone())
+}
+
+fn watch_known<T: Clone + Send + Sync + 'static>(
+    receiver: &watch::Receiver<Option<Versioned<T>>>,
+) -> impl Stream<Item = Versioned<T>> {
+    WatchStream::new(receiver.clone()).filter_map(futures::future::ready)
+}
+
+fn spawn_subscription<T, S, F, Fut>(
+    client: &KachakaApiClient,
+    snapshot: &watch::Sender<RobotSnapshot>,
+    tasks: &mut Vec<JoinHandle<()>>,
+    field: fn(&mut RobotSnapshot) -> &mut Option<Versioned<T>>,
+    subscribe: F,
+) -> watch::Receiver<Option<Versioned<T>>>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Stream<Item = Result<Versioned<T>, KachakaApiError>> + Send,
+    F: Fn(KachakaApiClient) -> Fut + Send + 'static,
+    Fut: Future<Output = S> + Send,
+{
+    let (latest, receiver) = watch::channel(None);
+    let client = client.clone();
+    let snapshot = snapshot.clone();
+    tasks.push(tokio::spawn(async move {
+        loop {
+            let mut stream = Box::pin(subscribe(client.clone()).await);
+            while let Some(Ok(value)) = stream.next().await {
+                // the snapshot first, so that it is never older than a field
+                snapshot.send_modify(|snapshot| *field(snapshot) = Some(value.clone()));
+                latest.send_replace(Some(value));
+            }
+            tokio::time::sleep(RESUBSCRIBE_INTERVAL).await;
+        }
+    }));
+    receiver
+}
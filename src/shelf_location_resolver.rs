@@ -1,15 +1,48 @@
-use futures::stream::StreamExt;
+use futures::future;
+use futures::stream::{Stream, StreamExt};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{broadcast, watch, RwLock};
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::BroadcastStream;
+use unicode_normalization::UnicodeNormalization;
 
 use crate::kachaka_api;
-use crate::KachakaApiClient;
+use crate::types::KachakaApiError;
+use crate::{KachakaApiClient, StartCommandOptions};
+
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(1);
+
+const MAX_SUGGESTIONS: usize = 5;
+const MIN_SUGGESTION_SIMILARITY: f64 = 0.4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutLookupError {
+    /// Nothing matches; `suggestions` lists similar names, most similar first.
+    NotFound {
+        name: String,
+        suggestions: Vec<String>,
+    },
+    /// Several items match equally well.
+    Ambiguous { name: String, ids: Vec<String> },
+}
+
+#[derive(Debug)]
+pub enum NamedCommandError {
+    ShelfLookupError(LayoutLookupError),
+    LocationLookupError(LayoutLookupError),
+    KachakaApiError(KachakaApiError),
+}
 
 struct LayoutCollection<T> {
     items: Vec<T>,
+    ids: Vec<String>,
+    names: Vec<String>,
+    normalized_names: Vec<String>,
     id_index: HashMap<String, usize>,
-    name_index: HashMap<String, usize>,
+    name_index: HashMap<String, Vec<usize>>,
+    normalized_name_index: HashMap<String, Vec<usize>>,
 }
 
 impl<T> LayoutCollection<T> {
@@ -18,18 +51,30 @@ impl<T> LayoutCollection<T> {
         get_id: impl Fn(&T) -> String,
         get_name: impl Fn(&T) -> String,
     ) -> Self {
+        let ids: Vec<String> = items.iter().map(&get_id).collect();
+        let names: Vec<String> = items.iter().map(&get_name).collect();
+        let normalized_names: Vec<String> = names.iter().map(|name| normalize_name(name)).collect();
         let mut id_index = HashMap::new();
-        let mut name_index = HashMap::new();
+        let mut name_index: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut normalized_name_index: HashMap<String, Vec<usize>> = HashMap::new();
 
-        for (idx, item) in items.iter().enumerate() {
-            id_index.insert(get_id(item), idx);
-            name_index.insert(get_name(item), idx);
+        for idx in 0..items.len() {
+            id_index.insert(ids[idx].clone(), idx);
+            name_index.entry(names[idx].clone()).or_default().push(idx);
+            normalized_name_index
+                .entry(normalized_names[idx].clone())
+                .or_default()
+                .push(idx);
         }
 
         Self {
             items,
+            ids,
+            names,
+            normalized_names,
             id_index,
             name_index,
+            normalized_name_index,
         }
     }
 
@@ -37,19 +82,143 @@ impl<T> LayoutCollection<T> {
         self.id_index.get(id).map(|&idx| &self.items[idx])
     }
 
+    /// The item with exactly this name, or `None` if there is none or several.
     pub fn get_by_name(&self, name: &str) -> Option<&T> {
-        self.name_index.get(name).map(|&idx| &self.items[idx])
+        match self.name_index.get(name).map(Vec::as_slice) {
+            Some(&[idx]) => Some(&self.items[idx]),
+            _ => None,
+        }
+    }
+
+    /// Accepts an exact name, an id, or a name that is equal after normalization, in
+    /// that order.
+    pub fn resolve(&self, name_or_id: &str) -> Result<&T, LayoutLookupError> {
+        if !self.name_index.contains_key(name_or_id) {
+            if let Some(item) = self.get_by_id(name_or_id) {
+                return Ok(item);
+            }
+        }
+        self.find_by_name(name_or_id)
+    }
+
+    /// Looks up an exact name first, then a name that is equal after normalization.
+    pub fn find_by_name(&self, name: &str) -> Result<&T, LayoutLookupError> {
+        let matches = self
+            .name_index
+            .get(name)
+            .or_else(|| self.normalized_name_index.get(&normalize_name(name)));
+        match matches.map(Vec::as_slice) {
+            Some(&[idx]) => Ok(&self.items[idx]),
+            Some(indices) if !indices.is_empty() => Err(LayoutLookupError::Ambiguous {
+                name: name.to_string(),
+                ids: indices.iter().map(|&idx| self.ids[idx].clone()).collect(),
+            }),
+            _ => Err(LayoutLookupError::NotFound {
+                name: name.to_string(),
+                suggestions: self
+                    .suggest(name, MAX_SUGGESTIONS)
+                    .into_iter()
+                    .map(|idx| self.names[idx].clone())
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Indices of the items with a name similar to `name`, most similar first.
+    pub fn suggest(&self, name: &str, limit: usize) -> Vec<usize> {
+        let normalized_name = normalize_name(name);
+        let mut scored: Vec<(f64, usize)> = self
+            .normalized_names
+            .iter()
+            .enumerate()
+            .map(|(idx, candidate)| (name_similarity(&normalized_name, candidate), idx))
+            .filter(|(similarity, _)| *similarity >= MIN_SUGGESTION_SIMILARITY)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.into_iter().take(limit).map(|(_, idx)| idx).collect()
+    }
+}
+
+/// NFKC (which folds full-width ASCII and half-width kana), lower case and collapsed
+/// white space.
+fn normalize_name(name: &str) -> String {
+    name.nfkc()
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 1.0 for equal names, 0.0 for completely different ones; a name containing the
+/// other scores at least 0.8.
+fn name_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 1.0;
+    }
+    let similarity = 1.0 - levenshtein(&a, &b) as f64 / max_len as f64;
+    let contains = |haystack: &[char], needle: &[char]| {
+        !needle.is_empty()
+            && haystack
+                .windows(needle.len())
+                .any(|window| window == needle)
+    };
+    if contains(&a, &b) || contains(&b, &a) {
+        similarity.max(0.8)
+    } else {
+        similarity
     }
 }
 
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 struct ShelfLocationResolverState {
     locations_collection: LayoutCollection<kachaka_api::Location>,
     shelves_collection: LayoutCollection<kachaka_api::Shelf>,
 }
 
+/// A change of one location or shelf between two layouts received from the robot.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutChange<T> {
+    Added(T),
+    Removed(T),
+    Renamed {
+        old_name: String,
+        item: T,
+    },
+    Moved {
+        old_pose: Option<kachaka_api::Pose>,
+        item: T,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutChangeEvent {
+    Location(LayoutChange<kachaka_api::Location>),
+    Shelf(LayoutChange<kachaka_api::Shelf>),
+}
+
 pub struct ShelfLocationResolver {
     kachaka_api_client: KachakaApiClient,
     state: Arc<RwLock<ShelfLocationResolverState>>,
+    /// Whether the locations and the shelves have been received at least once.
+    ready: watch::Sender<(bool, bool)>,
+    changes: broadcast::Sender<LayoutChangeEvent>,
+    stopped: watch::Sender<bool>,
 }
 
 impl ShelfLocationResolver {
@@ -68,35 +237,122 @@ impl ShelfLocationResolver {
                     |shelf| shelf.name.clone(),
                 ),
             })),
+            ready: watch::channel((false, false)).0,
+            changes: broadcast::channel(64).0,
+            stopped: watch::channel(false).0,
         }
     }
 
+    /// Creates a resolver and runs its update loop in a background task.
+    pub fn spawn(kachaka_api_client: KachakaApiClient) -> (Arc<Self>, JoinHandle<()>) {
+        let resolver = Arc::new(Self::new(kachaka_api_client));
+        let handle = tokio::spawn({
+            let resolver = resolver.clone();
+            async move { resolver.run_update_loop().await }
+        });
+        (resolver, handle)
+    }
+
+    /// Keeps the layout up to date until [`ShelfLocationResolver::stop`] is called.
+    ///
+    /// A failed subscription is started again after a short delay; the last received
+    /// layout is kept in the meantime.
     pub async fn run_update_loop(&self) {
-        let mut locations_stream = self.kachaka_api_client.clone().watch_locations().await;
-        let mut shelves_stream = self.kachaka_api_client.clone().watch_shelves().await;
+        let mut stopped = self.stopped.subscribe();
+        tokio::select! {
+            _ = stopped.wait_for(|stopped| *stopped) => {}
+            _ = async { tokio::join!(self.update_locations(), self.update_shelves()) } => {}
+        }
+    }
+
+    /// Makes running and future update loops return.
+    pub fn stop(&self) {
+        self.stopped.send_replace(true);
+    }
 
+    /// Waits until both the locations and the shelves have been received.
+    pub async fn wait_until_ready(&self) {
+        let _ = self
+            .ready
+            .subscribe()
+            .wait_for(|(locations, shelves)| *locations && *shelves)
+            .await;
+    }
+
+    pub fn is_ready(&self) -> bool {
+        let (locations, shelves) = *self.ready.borrow();
+        locations && shelves
+    }
+
+    /// Changes of the layout received after the call. The first layout is reported as
+    /// every location and shelf being added.
+    pub fn watch_changes(&self) -> impl Stream<Item = LayoutChangeEvent> {
+        BroadcastStream::new(self.changes.subscribe()).filter_map(|event| future::ready(event.ok()))
+    }
+
+    async fn update_locations(&self) {
         loop {
-            tokio::select! {
-                Some(locations) = locations_stream.next() => {
-                    let mut state = self.state.write().await;
-                    state.locations_collection = LayoutCollection::new(
-                        locations.unwrap(),
-                        |location| location.id.clone(),
-                        |location| location.name.clone(),
-                    );
+            let mut locations_stream = self.kachaka_api_client.clone().watch_locations().await;
+            while let Some(Ok(locations)) = locations_stream.next().await {
+                let mut state = self.state.write().await;
+                let changes =
+                    diff_layout(&state.locations_collection.items, &locations, |location| {
+                        (&location.id, &location.name, &location.pose)
+                    });
+                state.locations_collection = LayoutCollection::new(
+                    locations,
+                    |location| location.id.clone(),
+                    |location| location.name.clone(),
+                );
+                drop(state);
+                self.ready.send_modify(|(locations, _)| *locations = true);
+                for change in changes {
+                    let _ = self.changes.send(LayoutChangeEvent::Location(change));
                 }
-                Some(shelves) = shelves_stream.next() => {
-                    let mut state = self.state.write().await;
-                    state.shelves_collection = LayoutCollection::new(
-                        shelves.unwrap(),
-                        |shelf| shelf.id.clone(),
-                        |shelf| shelf.name.clone(),
-                    );
+            }
+            tokio::time::sleep(RESUBSCRIBE_INTERVAL).await;
+        }
+    }
+
+    async fn update_shelves(&self) {
+        loop {
+            let mut shelves_stream = self.kachaka_api_client.clone().watch_shelves().await;
+            while let Some(Ok(shelves)) = shelves_stream.next().await {
+                let mut state = self.state.write().await;
+                let changes = diff_layout(&state.shelves_collection.items, &shelves, |shelf| {
+                    (&shelf.id, &shelf.name, &shelf.pose)
+                });
+                state.shelves_collection = LayoutCollection::new(
+                    shelves,
+                    |shelf| shelf.id.clone(),
+                    |shelf| shelf.name.clone(),
+                );
+                drop(state);
+                self.ready.send_modify(|(_, shelves)| *shelves = true);
+                for change in changes {
+                    let _ = self.changes.send(LayoutChangeEvent::Shelf(change));
                 }
             }
+            tokio::time::sleep(RESUBSCRIBE_INTERVAL).await;
         }
     }
 
+    pub async fn resolve_location(
+        &self,
+        name_or_id: &str,
+    ) -> Result<kachaka_api::Location, LayoutLookupError> {
+        let state = self.state.read().await;
+        state.locations_collection.resolve(name_or_id).cloned()
+    }
+
+    pub async fn resolve_shelf(
+        &self,
+        name_or_id: &str,
+    ) -> Result<kachaka_api::Shelf, LayoutLookupError> {
+        let state = self.state.read().await;
+        state.shelves_collection.resolve(name_or_id).cloned()
+    }
+
     pub async fn get_location_by_id(&self, id: &str) -> Option<kachaka_api::Location> {
         let state = self.state.read().await;
         state.locations_collection.get_by_id(id).cloned()
@@ -107,6 +363,25 @@ impl ShelfLocationResolver {
         state.locations_collection.get_by_name(name).cloned()
     }
 
+    pub async fn find_location_by_name(
+        &self,
+        name: &str,
+    ) -> Result<kachaka_api::Location, LayoutLookupError> {
+        let state = self.state.read().await;
+        state.locations_collection.find_by_name(name).cloned()
+    }
+
+    /// Locations with a name similar to `name`, most similar first.
+    pub async fn suggest_locations(&self, name: &str, limit: usize) -> Vec<kachaka_api::Location> {
+        let state = self.state.read().await;
+        let collection = &state.locations_collection;
+        collection
+            .suggest(name, limit)
+            .into_iter()
+            .map(|idx| collection.items[idx].clone())
+            .collect()
+    }
+
     pub async fn get_all_locations(&self) -> Vec<kachaka_api::Location> {
         self.state.read().await.locations_collection.items.clone()
     }
@@ -121,7 +396,136 @@ impl ShelfLocationResolver {
         state.shelves_collection.get_by_name(name).cloned()
     }
 
+    pub async fn find_shelf_by_name(
+        &self,
+        name: &str,
+    ) -> Result<kachaka_api::Shelf, LayoutLookupError> {
+        let state = self.state.read().await;
+        state.shelves_collection.find_by_name(name).cloned()
+    }
+
+    /// Shelves with a name similar to `name`, most similar first.
+    pub async fn suggest_shelves(&self, name: &str, limit: usize) -> Vec<kachaka_api::Shelf> {
+        let state = self.state.read().await;
+        let collection = &state.shelves_collection;
+        collection
+            .suggest(name, limit)
+            .into_iter()
+            .map(|idx| collection.items[idx].clone())
+            .collect()
+    }
+
     pub async fn get_all_shelves(&self) -> Vec<kachaka_api::Shelf> {
         self.state.read().await.shelves_collection.items.clone()
     }
 }
+
+// name-based commands
+// Shelves and locations are given by name or id and resolved with
+// `resolve_shelf`/`resolve_location`; nothing is sent when a lookup fails.
+impl ShelfLocationResolver {
+    pub async fn move_shelf_by_name(
+        &self,
+        shelf: &str,
+        location: &str,
+        options: StartCommandOptions,
+    ) -> Result<String, NamedCommandError> {
+        let shelf = self
+            .resolve_shelf(shelf)
+            .await
+            .map_err(NamedCommandError::ShelfLookupError)?;
+        let location = self
+            .resolve_location(location)
+            .await
+            .map_err(NamedCommandError::LocationLookupError)?;
+        self.kachaka_api_client
+            .clone()
+            .move_shelf(&shelf.id, &location.id, options)
+            .await
+            .map_err(NamedCommandError::KachakaApiError)
+    }
+
+    pub async fn return_shelf_by_name(
+        &self,
+        shelf: &str,
+        options: StartCommandOptions,
+    ) -> Result<String, NamedCommandError> {
+        let shelf = self
+            .resolve_shelf(shelf)
+            .await
+            .map_err(NamedCommandError::ShelfLookupError)?;
+        self.kachaka_api_client
+            .clone()
+            .return_shelf(&shelf.id, options)
+            .await
+            .map_err(NamedCommandError::KachakaApiError)
+    }
+
+    pub async fn move_to_location_by_name(
+        &self,
+        location: &str,
+        options: StartCommandOptions,
+    ) -> Result<String, NamedCommandError> {
+        let location = self
+            .resolve_location(location)
+            .await
+            .map_err(NamedCommandError::LocationLookupError)?;
+        self.kachaka_api_client
+            .clone()
+            .move_to_location(&location.id, options)
+            .await
+            .map_err(NamedCommandError::KachakaApiError)
+    }
+
+    pub async fn dock_any_shelf_with_registration_by_name(
+        &self,
+        location: &str,
+        options: StartCommandOptions,
+    ) -> Result<String, NamedCommandError> {
+        let location = self
+            .resolve_location(location)
+            .await
+            .map_err(NamedCommandError::LocationLookupError)?;
+        self.kachaka_api_client
+            .clone()
+            .dock_any_shelf_with_registration(&location.id, options)
+            .await
+            .map_err(NamedCommandError::KachakaApiError)
+    }
+}
+
+fn diff_layout<T: Clone>(
+    old: &[T],
+    new: &[T],
+    fields: impl Fn(&T) -> (&String, &String, &Option<kachaka_api::Pose>),
+) -> Vec<LayoutChange<T>> {
+    let mut changes = Vec::new();
+    for item in new {
+        let (id, name, pose) = fields(item);
+        match old.iter().find(|old_item| fields(old_item).0 == id) {
+            None => changes.push(LayoutChange::Added(item.clone())),
+            Some(old_item) => {
+                let (_, old_name, old_pose) = fields(old_item);
+                if old_name != name {
+                    changes.push(LayoutChange::Renamed {
+                        old_name: old_name.clone(),
+                        item: item.clone(),
+                    });
+                }
+                if old_pose != pose {
+                    changes.push(LayoutChange::Moved {
+                        old_pose: *old_pose,
+                        item: item.clone(),
+                    });
+                }
+            }
+        }
+    }
+    for old_item in old {
+        let id = fields(old_item).0;
+        if !new.iter().any(|item| fields(item).0 == id) {
+            changes.push(LayoutChange::Removed(old_item.clone()));
+        }
+    }
+    changes
+}
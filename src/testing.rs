@@ -0,0 +1,835 @@
+// Responses are built with `..Default::default()` so the fake keeps compiling when the
+// robot's proto gains fields.
+#![allow(clippy::needless_update)]
+
+use hyper_util::rt::TokioIo;
+use prost::Message;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::{Endpoint, Server, Uri};
+use tonic::{Request, Response, Status};
+
+use crate::kachaka_api;
+use crate::kachaka_api::kachaka_api_server::{KachakaApi, KachakaApiServer};
+use crate::recording::{Record, RecordTopic};
+use crate::types::{BatteryInfo, Command, KachakaError, Pose, PowerSupplyStatus};
+use crate::{KachakaApiClient, StartCommandOptions};
+
+pub mod simulator;
+
+// The fake reports its own error codes; they are not taken from the robot's error table.
+/// Reported for a command cancelled through `CancelCommand` or by a `cancel_all` command.
+pub const CANCELLED_ERROR_CODE: i32 = 10001;
+/// Reported by `ResetShelfPose` and the simulator for an unknown shelf id.
+pub const SHELF_NOT_FOUND_ERROR_CODE: i32 = 10002;
+/// Reported by the simulator for an unknown location id.
+pub const LOCATION_NOT_FOUND_ERROR_CODE: i32 = 10003;
+/// Reported by the simulator when a shelf command cannot be carried out, e.g. no shelf
+/// to dock or another shelf already docked.
+pub const SHELF_UNAVAILABLE_ERROR_CODE: i32 = 10004;
+
+/// A value served through the cursor based long-poll getters.
+///
+/// A request with the current cursor blocks until the value changes; any other
+/// cursor (including 0) returns the latest value immediately.
+struct Topic<T> {
+    sender: watch::Sender<(i64, T)>,
+}
+
+impl<T: Clone> Topic<T> {
+    fn new(value: T) -> Self {
+        Self {
+            sender: watch::channel((1, value)).0,
+        }
+    }
+
+    fn set(&self, value: T) {
+        self.sender
+            .send_modify(|(cursor, current)| (*cursor, *current) = (*cursor + 1, value));
+    }
+
+    fn modify(&self, modify: impl FnOnce(&mut T)) {
+        self.sender.send_modify(|(cursor, current)| {
+            *cursor += 1;
+            modify(current);
+        });
+    }
+
+    fn latest(&self) -> T {
+        self.sender.borrow().1.clone()
+    }
+
+    async fn get(&self, cursor: i64) -> (i64, T) {
+        let mut receiver = self.sender.subscribe();
+        loop {
+            {
+                let value = receiver.borrow_and_update();
+                if value.0 != cursor {
+                    return value.clone();
+                }
+            }
+            if receiver.changed().await.is_err() {
+                return self.sender.borrow().clone();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StartedCommand {
+    pub command_id: String,
+    pub command: Command,
+    pub options: StartCommandOptions,
+}
+
+#[derive(Default)]
+struct CommandQueue {
+    next_command_id: usize,
+    running: Option<StartedCommand>,
+    pending: VecDeque<StartedCommand>,
+    history: Vec<StartedCommand>,
+}
+
+struct FakeKachakaState {
+    robot_serial_number: Topic<String>,
+    robot_version: Topic<String>,
+    robot_pose: Topic<kachaka_api::Pose>,
+    battery_info: Topic<(f64, i32)>,
+    front_camera_ros_image: Topic<kachaka_api::RosImage>,
+    front_camera_ros_compressed_image: Topic<kachaka_api::RosCompressedImage>,
+    back_camera_ros_image: Topic<kachaka_api::RosImage>,
+    back_camera_ros_compressed_image: Topic<kachaka_api::RosCompressedImage>,
+    error: Topic<Vec<i32>>,
+    command_state: Topic<kachaka_api::GetCommandStateResponse>,
+    last_command_result: Topic<kachaka_api::GetLastCommandResultResponse>,
+    png_map: Topic<kachaka_api::Map>,
+    locations: Topic<Vec<kachaka_api::Location>>,
+    shelves: Topic<Vec<kachaka_api::Shelf>>,
+    moving_shelf_id: Topic<String>,
+    robot_error_code_json: Mutex<String>,
+    commands: Mutex<CommandQueue>,
+    running_command: watch::Sender<Option<(String, Command)>>,
+    auto_complete: AtomicBool,
+    proceed_count: AtomicUsize,
+}
+
+/// An in-process implementation of the Kachaka gRPC service backed by in-memory state.
+///
+/// Every setter bumps the cursor of the corresponding getter, so `watch_*` streams of
+/// a connected [`KachakaApiClient`] observe the change. Commands are queued and, by
+/// default, complete successfully as soon as they start; call
+/// [`FakeKachaka::set_auto_complete`] with `false` to finish them from the test with
+/// [`FakeKachaka::complete_command`].
+#[derive(Clone)]
+pub struct FakeKachaka {
+    state: Arc<FakeKachakaState>,
+}
+
+impl Default for FakeKachaka {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeKachaka {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(FakeKachakaState {
+                robot_serial_number: Topic::new("FAKE0000000".to_string()),
+                robot_version: Topic::new("0.0.0".to_string()),
+                robot_pose: Topic::new(kachaka_api::Pose::default()),
+                battery_info: Topic::new((
+                    100.0,
+                    kachaka_api::PowerSupplyStatus::Discharging as i32,
+                )),
+                front_camera_ros_image: Topic::new(kachaka_api::RosImage::default()),
+                front_camera_ros_compressed_image: Topic::new(
+                    kachaka_api::RosCompressedImage::default(),
+                ),
+                back_camera_ros_image: Topic::new(kachaka_api::RosImage::default()),
+                back_camera_ros_compressed_image: Topic::new(
+                    kachaka_api::RosCompressedImage::default(),
+                ),
+                error: Topic::new(Vec::new()),
+                command_state: Topic::new(kachaka_api::GetCommandStateResponse {
+                    state: kachaka_api::CommandState::Unspecified as i32,
+                    ..Default::default()
+                }),
+                last_command_result: Topic::new(
+                    kachaka_api::GetLastCommandResultResponse::default(),
+                ),
+                png_map: Topic::new(kachaka_api::Map::default()),
+                locations: Topic::new(Vec::new()),
+                shelves: Topic::new(Vec::new()),
+                moving_shelf_id: Topic::new(String::new()),
+                robot_error_code_json: Mutex::new("[]".to_string()),
+                commands: Mutex::new(CommandQueue::default()),
+                running_command: watch::channel(None).0,
+                auto_complete: AtomicBool::new(true),
+                proceed_count: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Serves the fake on an ephemeral port of `127.0.0.1` and returns its address.
+    pub async fn spawn_on_local_port(&self) -> std::io::Result<SocketAddr> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let service = KachakaApiServer::new(self.clone());
+        tokio::spawn(
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        Ok(addr)
+    }
+
+    /// Connects a client to the fake through an in-memory duplex stream.
+    pub async fn connect_in_memory(&self) -> Result<KachakaApiClient, tonic::transport::Error> {
+        let (client_io, server_io) = tokio::io::duplex(1024 * 1024);
+        let service = KachakaApiServer::new(self.clone());
+        tokio::spawn(
+            Server::builder()
+                .add_service(service)
+                .serve_with_incoming(tokio_stream::once(Ok::<_, std::io::Error>(server_io))),
+        );
+
+        let mut client_io = Some(client_io);
+        let channel = Endpoint::try_from("http://[::]:26400")?
+            .connect_with_connector(tower::service_fn(move |_: Uri| {
+                let client_io = client_io.take();
+                async move {
+                    client_io.map(TokioIo::new).ok_or_else(|| {
+                        std::io::Error::other("in-memory connection is already in use")
+                    })
+                }
+            }))
+            .await?;
+        Ok(KachakaApiClient::new(channel))
+    }
+
+    pub fn into_service(self) -> KachakaApiServer<Self> {
+        KachakaApiServer::new(self)
+    }
+
+    // state setters
+    pub fn set_robot_serial_number(&self, serial_number: &str) {
+        self.state
+            .robot_serial_number
+            .set(serial_number.to_string());
+    }
+
+    pub fn set_robot_version(&self, version: &str) {
+        self.state.robot_version.set(version.to_string());
+    }
+
+    pub fn set_robot_pose(&self, pose: Pose) {
+        self.state.robot_pose.set(pose.into());
+    }
+
+    pub fn robot_pose(&self) -> Pose {
+        self.state.robot_pose.latest().into()
+    }
+
+    pub fn set_battery_info(&self, battery_info: BatteryInfo) {
+        self.state.battery_info.set((
+            battery_info.remaining_percentage,
+            kachaka_api::PowerSupplyStatus::from(battery_info.power_supply_status) as i32,
+        ));
+    }
+
+    pub fn battery_info(&self) -> BatteryInfo {
+        let (remaining_percentage, power_supply_status) = self.state.battery_info.latest();
+        BatteryInfo {
+            power_supply_status: PowerSupplyStatus::from(power_supply_status),
+            remaining_percentage,
+        }
+    }
+
+    pub fn set_front_camera_ros_image(&self, image: kachaka_api::RosImage) {
+        self.state.front_camera_ros_image.set(image);
+    }
+
+    pub fn set_front_camera_ros_compressed_image(&self, image: kachaka_api::RosCompressedImage) {
+        self.state.front_camera_ros_compressed_image.set(image);
+    }
+
+    pub fn set_back_camera_ros_image(&self, image: kachaka_api::RosImage) {
+        self.state.back_camera_ros_image.set(image);
+    }
+
+    pub fn set_back_camera_ros_compressed_image(&self, image: kachaka_api::RosCompressedImage) {
+        self.state.back_camera_ros_compressed_image.set(image);
+    }
+
+    pub fn set_error(&self, errors: Vec<KachakaError>) {
+        self.state
+            .error
+            .set(errors.into_iter().map(|error| error.error_code).collect());
+    }
+
+    pub fn set_robot_error_code_json(&self, json: &str) {
+        *self.state.robot_error_code_json.lock().unwrap() = json.to_string();
+    }
+
+    pub fn set_png_map(&self, map: kachaka_api::Map) {
+        self.state.png_map.set(map);
+    }
+
+    pub fn set_locations(&self, locations: Vec<kachaka_api::Location>) {
+        self.state.locations.set(locations);
+    }
+
+    pub fn locations(&self) -> Vec<kachaka_api::Location> {
+        self.state.locations.latest()
+    }
+
+    pub fn set_shelves(&self, shelves: Vec<kachaka_api::Shelf>) {
+        self.state.shelves.set(shelves);
+    }
+
+    pub fn shelves(&self) -> Vec<kachaka_api::Shelf> {
+        self.state.shelves.latest()
+    }
+
+    pub fn update_shelf(&self, shelf_id: &str, update: impl FnOnce(&mut kachaka_api::Shelf)) {
+        self.state.shelves.modify(|shelves| {
+            if let Some(shelf) = shelves.iter_mut().find(|shelf| shelf.id == shelf_id) {
+                update(shelf);
+            }
+        });
+    }
+
+    pub fn set_moving_shelf_id(&self, shelf_id: &str) {
+        self.state.moving_shelf_id.set(shelf_id.to_string());
+    }
+
+    pub fn moving_shelf_id(&self) -> String {
+        self.state.moving_shelf_id.latest()
+    }
+
+    /// Publishes a recorded response; undecodable payloads are ignored.
+    pub(crate) fn apply_record(&self, record: &Record) {
+        let payload = record.payload.as_slice();
+        let state = &self.state;
+        match record.topic {
+            RecordTopic::RobotPose => {
+                if let Ok(kachaka_api::GetRobotPoseResponse {
+                    pose: Some(pose), ..
+                }) = kachaka_api::GetRobotPoseResponse::decode(payload)
+                {
+                    state.robot_pose.set(pose);
+                }
+            }
+            RecordTopic::BatteryInfo => {
+                if let Ok(response) = kachaka_api::GetBatteryInfoResponse::decode(payload) {
+                    state
+                        .battery_info
+                        .set((response.remaining_percentage, response.power_supply_status));
+                }
+            }
+            RecordTopic::Error => {
+                if let Ok(response) = kachaka_api::GetErrorResponse::decode(payload) {
+                    state.error.set(response.error_codes);
+                }
+            }
+            RecordTopic::CommandState => {
+                if let Ok(response) = kachaka_api::GetCommandStateResponse::decode(payload) {
+                    state.command_state.set(response);
+                }
+            }
+            RecordTopic::LastCommandResult => {
+                if let Ok(response) = kachaka_api::GetLastCommandResultResponse::decode(payload) {
+                    state.last_command_result.set(response);
+                }
+            }
+            RecordTopic::FrontCameraRosImage => {
+                if let Ok(kachaka_api::GetRosImageResponse {
+                    image: Some(image), ..
+                }) = kachaka_api::GetRosImageResponse::decode(payload)
+                {
+                    state.front_camera_ros_image.set(image);
+                }
+            }
+            RecordTopic::FrontCameraRosCompressedImage => {
+                if let Ok(kachaka_api::GetRosCompressedImageResponse {
+                    image: Some(image), ..
+                }) = kachaka_api::GetRosCompressedImageResponse::decode(payload)
+                {
+                    state.front_camera_ros_compressed_image.set(image);
+                }
+            }
+            RecordTopic::BackCameraRosImage => {
+                if let Ok(kachaka_api::GetRosImageResponse {
+                    image: Some(image), ..
+                }) = kachaka_api::GetRosImageResponse::decode(payload)
+                {
+                    state.back_camera_ros_image.set(image);
+                }
+            }
+            RecordTopic::BackCameraRosCompressedImage => {
+                if let Ok(kachaka_api::GetRosCompressedImageResponse {
+                    image: Some(image), ..
+                }) = kachaka_api::GetRosCompressedImageResponse::decode(payload)
+                {
+                    state.back_camera_ros_compressed_image.set(image);
+                }
+            }
+            RecordTopic::Locations => {
+                if let Ok(response) = kachaka_api::GetLocationsResponse::decode(payload) {
+                    state.locations.set(response.locations);
+                }
+            }
+            RecordTopic::Shelves => {
+                if let Ok(response) = kachaka_api::GetShelvesResponse::decode(payload) {
+                    state.shelves.set(response.shelves);
+                }
+            }
+            RecordTopic::MovingShelfId => {
+                if let Ok(response) = kachaka_api::GetMovingShelfIdResponse::decode(payload) {
+                    state.moving_shelf_id.set(response.shelf_id);
+                }
+            }
+        }
+    }
+
+    // commands
+    /// When enabled (the default), every command succeeds as soon as it starts.
+    pub fn set_auto_complete(&self, auto_complete: bool) {
+        self.state
+            .auto_complete
+            .store(auto_complete, Ordering::SeqCst);
+    }
+
+    /// All commands received through `StartCommand`, in order.
+    pub fn started_commands(&self) -> Vec<StartedCommand> {
+        self.state.commands.lock().unwrap().history.clone()
+    }
+
+    pub fn running_command(&self) -> Option<(String, Command)> {
+        self.state.running_command.borrow().clone()
+    }
+
+    /// Notifies about the command that is currently running (id and command).
+    pub fn watch_running_command(&self) -> watch::Receiver<Option<(String, Command)>> {
+        self.state.running_command.subscribe()
+    }
+
+    /// Finishes the running command with `result` and starts the next queued one.
+    ///
+    /// Returns `false` if no command was running.
+    pub fn complete_command(&self, result: Result<(), KachakaError>) -> bool {
+        let mut commands = self.state.commands.lock().unwrap();
+        self.complete_running_command(&mut commands, result)
+    }
+
+    /// Like [`FakeKachaka::complete_command`], but only if `command_id` is still running.
+    pub fn complete_command_by_id(
+        &self,
+        command_id: &str,
+        result: Result<(), KachakaError>,
+    ) -> bool {
+        let mut commands = self.state.commands.lock().unwrap();
+        if commands
+            .running
+            .as_ref()
+            .is_some_and(|running| running.command_id == command_id)
+        {
+            self.complete_running_command(&mut commands, result)
+        } else {
+            false
+        }
+    }
+
+    pub fn proceed_count(&self) -> usize {
+        self.state.proceed_count.load(Ordering::SeqCst)
+    }
+
+    fn complete_running_command(
+        &self,
+        commands: &mut CommandQueue,
+        result: Result<(), KachakaError>,
+    ) -> bool {
+        let Some(finished) = commands.running.take() else {
+            return false;
+        };
+        self.state
+            .last_command_result
+            .set(kachaka_api::GetLastCommandResultResponse {
+                result: Some(result.into()),
+                command: Some(finished.command.into()),
+                ..Default::default()
+            });
+        self.start_next_command(commands);
+        true
+    }
+
+    fn start_next_command(&self, commands: &mut CommandQueue) {
+        while commands.running.is_none() {
+            let Some(next) = commands.pending.pop_front() else {
+                self.state
+                    .command_state
+                    .set(kachaka_api::GetCommandStateResponse {
+                        state: kachaka_api::CommandState::Unspecified as i32,
+                        ..Default::default()
+                    });
+                self.state.running_command.send_replace(None);
+                return;
+            };
+            self.state
+                .command_state
+                .set(kachaka_api::GetCommandStateResponse {
+                    state: kachaka_api::CommandState::Running as i32,
+                    command: Some(next.command.clone().into()),
+                    command_id: next.command_id.clone(),
+                    ..Default::default()
+                });
+            self.state
+                .running_command
+                .send_replace(Some((next.command_id.clone(), next.command.clone())));
+            commands.running = Some(next);
+            if self.state.auto_complete.load(Ordering::SeqCst) {
+                self.complete_running_command(commands, Ok(()));
+            }
+        }
+    }
+
+    fn start_command(&self, command: Command, options: StartCommandOptions) -> String {
+        let mut commands = self.state.commands.lock().unwrap();
+        if options.cancel_all {
+            commands.pending.clear();
+            self.complete_running_command(
+                &mut commands,
+                Err(KachakaError {
+                    error_code: CANCELLED_ERROR_CODE,
+                }),
+            );
+        }
+        commands.next_command_id += 1;
+        let started = StartedCommand {
+            command_id: format!("fake-command-{}", commands.next_command_id),
+            command,
+            options,
+        };
+        let command_id = started.command_id.clone();
+        commands.history.push(started.clone());
+        commands.pending.push_back(started);
+        self.start_next_command(&mut commands);
+        command_id
+    }
+}
+
+fn metadata(cursor: i64) -> Option<kachaka_api::Metadata> {
+    Some(kachaka_api::Metadata { cursor })
+}
+
+fn request_cursor(request: Request<kachaka_api::GetRequest>) -> i64 {
+    request
+        .into_inner()
+        .metadata
+        .map(|metadata| metadata.cursor)
+        .unwrap_or_default()
+}
+
+fn success() -> Option<kachaka_api::Result> {
+    Some(Ok(()).into())
+}
+
+#[tonic::async_trait]
+impl KachakaApi for FakeKachaka {
+    async fn get_robot_serial_number(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetRobotSerialNumberResponse>, Status> {
+        let (cursor, serial_number) = self
+            .state
+            .robot_serial_number
+            .get(request_cursor(request))
+            .await;
+        Ok(Response::new(kachaka_api::GetRobotSerialNumberResponse {
+            metadata: metadata(cursor),
+            serial_number,
+            ..Default::default()
+        }))
+    }
+
+    async fn get_robot_version(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetRobotVersionResponse>, Status> {
+        let (cursor, version) = self.state.robot_version.get(request_cursor(request)).await;
+        Ok(Response::new(kachaka_api::GetRobotVersionResponse {
+            metadata: metadata(cursor),
+            version,
+            ..Default::default()
+        }))
+    }
+
+    async fn get_robot_pose(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetRobotPoseResponse>, Status> {
+        let (cursor, pose) = self.state.robot_pose.get(request_cursor(request)).await;
+        Ok(Response::new(kachaka_api::GetRobotPoseResponse {
+            metadata: metadata(cursor),
+            pose: Some(pose),
+            ..Default::default()
+        }))
+    }
+
+    async fn get_battery_info(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetBatteryInfoResponse>, Status> {
+        let (cursor, (remaining_percentage, power_supply_status)) =
+            self.state.battery_info.get(request_cursor(request)).await;
+        Ok(Response::new(kachaka_api::GetBatteryInfoResponse {
+            metadata: metadata(cursor),
+            remaining_percentage,
+            power_supply_status,
+            ..Default::default()
+        }))
+    }
+
+    async fn get_front_camera_ros_image(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetRosImageResponse>, Status> {
+        let (cursor, image) = self
+            .state
+            .front_camera_ros_image
+            .get(request_cursor(request))
+            .await;
+        Ok(Response::new(kachaka_api::GetRosImageResponse {
+            metadata: metadata(cursor),
+            image: Some(image),
+            ..Default::default()
+        }))
+    }
+
+    async fn get_front_camera_ros_compressed_image(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetRosCompressedImageResponse>, Status> {
+        let (cursor, image) = self
+            .state
+            .front_camera_ros_compressed_image
+            .get(request_cursor(request))
+            .await;
+        Ok(Response::new(kachaka_api::GetRosCompressedImageResponse {
+            metadata: metadata(cursor),
+            image: Some(image),
+            ..Default::default()
+        }))
+    }
+
+    async fn get_back_camera_ros_image(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetRosImageResponse>, Status> {
+        let (cursor, image) = self
+            .state
+            .back_camera_ros_image
+            .get(request_cursor(request))
+            .await;
+        Ok(Response::new(kachaka_api::GetRosImageResponse {
+            metadata: metadata(cursor),
+            image: Some(image),
+            ..Default::default()
+        }))
+    }
+
+    async fn get_back_camera_ros_compressed_image(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetRosCompressedImageResponse>, Status> {
+        let (cursor, image) = self
+            .state
+            .back_camera_ros_compressed_image
+            .get(request_cursor(request))
+            .await;
+        Ok(Response::new(kachaka_api::GetRosCompressedImageResponse {
+            metadata: metadata(cursor),
+            image: Some(image),
+            ..Default::default()
+        }))
+    }
+
+    async fn get_robot_error_code_json(
+        &self,
+        _request: Request<kachaka_api::EmptyRequest>,
+    ) -> Result<Response<kachaka_api::GetRobotErrorCodeJsonResponse>, Status> {
+        Ok(Response::new(kachaka_api::GetRobotErrorCodeJsonResponse {
+            result: success(),
+            json: self.state.robot_error_code_json.lock().unwrap().clone(),
+            ..Default::default()
+        }))
+    }
+
+    async fn get_error(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetErrorResponse>, Status> {
+        let (cursor, error_codes) = self.state.error.get(request_cursor(request)).await;
+        Ok(Response::new(kachaka_api::GetErrorResponse {
+            metadata: metadata(cursor),
+            error_codes,
+            ..Default::default()
+        }))
+    }
+
+    async fn get_command_state(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetCommandStateResponse>, Status> {
+        let (cursor, response) = self.state.command_state.get(request_cursor(request)).await;
+        Ok(Response::new(kachaka_api::GetCommandStateResponse {
+            metadata: metadata(cursor),
+            ..response
+        }))
+    }
+
+    async fn get_last_command_result(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetLastCommandResultResponse>, Status> {
+        let (cursor, response) = self
+            .state
+            .last_command_result
+            .get(request_cursor(request))
+            .await;
+        Ok(Response::new(kachaka_api::GetLastCommandResultResponse {
+            metadata: metadata(cursor),
+            ..response
+        }))
+    }
+
+    async fn start_command(
+        &self,
+        request: Request<kachaka_api::StartCommandRequest>,
+    ) -> Result<Response<kachaka_api::StartCommandResponse>, Status> {
+        let request = request.into_inner();
+        let Some(command) = request.command.and_then(|command| command.command) else {
+            return Err(Status::invalid_argument("command is not set"));
+        };
+        let options = StartCommandOptions {
+            title: request.title,
+            tts_on_success: request.tts_on_success,
+            cancel_all: request.cancel_all,
+            deferrable: request.deferrable,
+            lock_on_end: request.lock_on_end,
+        };
+        let command_id = FakeKachaka::start_command(self, command.into(), options);
+        Ok(Response::new(kachaka_api::StartCommandResponse {
+            result: success(),
+            command_id,
+            ..Default::default()
+        }))
+    }
+
+    async fn cancel_command(
+        &self,
+        _request: Request<kachaka_api::EmptyRequest>,
+    ) -> Result<Response<kachaka_api::CancelCommandResponse>, Status> {
+        let mut commands = self.state.commands.lock().unwrap();
+        let cancelled = commands
+            .running
+            .as_ref()
+            .map(|running| running.command.clone());
+        self.complete_running_command(
+            &mut commands,
+            Err(KachakaError {
+                error_code: CANCELLED_ERROR_CODE,
+            }),
+        );
+        Ok(Response::new(kachaka_api::CancelCommandResponse {
+            result: success(),
+            command: cancelled.map(Into::into),
+            ..Default::default()
+        }))
+    }
+
+    async fn proceed(
+        &self,
+        _request: Request<kachaka_api::EmptyRequest>,
+    ) -> Result<Response<kachaka_api::ProceedResponse>, Status> {
+        self.state.proceed_count.fetch_add(1, Ordering::SeqCst);
+        Ok(Response::new(kachaka_api::ProceedResponse {
+            result: success(),
+            ..Default::default()
+        }))
+    }
+
+    async fn get_png_map(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetPngMapResponse>, Status> {
+        let (cursor, map) = self.state.png_map.get(request_cursor(request)).await;
+        Ok(Response::new(kachaka_api::GetPngMapResponse {
+            metadata: metadata(cursor),
+            map: Some(map),
+        }))
+    }
+
+    async fn get_locations(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetLocationsResponse>, Status> {
+        let (cursor, locations) = self.state.locations.get(request_cursor(request)).await;
+        Ok(Response::new(kachaka_api::GetLocationsResponse {
+            metadata: metadata(cursor),
+            locations,
+            ..Default::default()
+        }))
+    }
+
+    async fn get_shelves(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetShelvesResponse>, Status> {
+        let (cursor, shelves) = self.state.shelves.get(request_cursor(request)).await;
+        Ok(Response::new(kachaka_api::GetShelvesResponse {
+            metadata: metadata(cursor),
+            shelves,
+            ..Default::default()
+        }))
+    }
+
+    async fn get_moving_shelf_id(
+        &self,
+        request: Request<kachaka_api::GetRequest>,
+    ) -> Result<Response<kachaka_api::GetMovingShelfIdResponse>, Status> {
+        let (cursor, shelf_id) = self
+            .state
+            .moving_shelf_id
+            .get(request_cursor(request))
+            .await;
+        Ok(Response::new(kachaka_api::GetMovingShelfIdResponse {
+            metadata: metadata(cursor),
+            shelf_id,
+            ..Default::default()
+        }))
+    }
+
+    async fn reset_shelf_pose(
+        &self,
+        request: Request<kachaka_api::ResetShelfPoseRequest>,
+    ) -> Result<Response<kachaka_api::ResetShelfPoseResponse>, Status> {
+        let shelf_id = request.into_inner().shelf_id;
+        let result = if self.shelves().iter().any(|shelf| shelf.id == shelf_id) {
+            Ok(())
+        } else {
+            Err(KachakaError {
+                error_code: SHELF_NOT_FOUND_ERROR_CODE,
+            })
+        };
+        Ok(Response::new(kachaka_api::ResetShelfPoseResponse {
+            result: Some(result.into()),
+            ..Default::default()
+        }))
+    }
+}
@@ -0,0 +1,455 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::{
+    FakeKachaka, LOCATION_NOT_FOUND_ERROR_CODE, SHELF_NOT_FOUND_ERROR_CODE,
+    SHELF_UNAVAILABLE_ERROR_CODE,
+};
+use crate::geometry::{angle_difference, normalize_angle};
+use crate::kachaka_api;
+use crate::types::{BatteryInfo, Command, KachakaError, Pose, PowerSupplyStatus};
+
+const POSITION_TOLERANCE: f64 = 0.01;
+const ANGLE_TOLERANCE: f64 = 0.01;
+
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    /// Translational speed in m/s.
+    pub linear_speed: f64,
+    /// Rotational speed in rad/s.
+    pub angular_speed: f64,
+    /// Wall-clock interval between simulation steps.
+    pub tick: Duration,
+    /// Simulated seconds per wall-clock second.
+    pub time_scale: f64,
+    /// Pose of the charger. The robot charges while standing still here.
+    pub home_pose: Pose,
+    /// Battery drain in percent per second while idle.
+    pub idle_discharge_rate: f64,
+    /// Battery drain in percent per second while moving.
+    pub moving_discharge_rate: f64,
+    /// Battery charge in percent per second at home.
+    pub charge_rate: f64,
+    /// Time spent per character by `Speak`.
+    pub speak_seconds_per_char: f64,
+    /// Maximum distance to a shelf for `DockShelf` and `DockAnyShelfWithRegistration`.
+    pub dock_distance: f64,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            linear_speed: 0.3,
+            angular_speed: 1.0,
+            tick: Duration::from_millis(50),
+            time_scale: 1.0,
+            home_pose: Pose {
+                x: 0.0,
+                y: 0.0,
+                theta: 0.0,
+            },
+            idle_discharge_rate: 0.002,
+            moving_discharge_rate: 0.01,
+            charge_rate: 0.03,
+            speak_seconds_per_char: 0.15,
+            dock_distance: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Action {
+    Navigate { x: f64, y: f64, yaw: Option<f64> },
+    RotateBy { remaining: f64 },
+    Forward { remaining: f64, speed: f64 },
+    Wait { remaining: f64 },
+    Dock { shelf_id: Option<String> },
+    Place { pose: Option<kachaka_api::Pose> },
+    Fail { error_code: i32 },
+}
+
+enum Progress {
+    Running,
+    Done,
+    Failed(i32),
+}
+
+/// Executes the commands received by a [`FakeKachaka`] over simulated time.
+///
+/// Motion commands move the robot pose at the configured speeds, shelf commands update
+/// the moving shelf id and the shelf poses, and the battery drains while away from the
+/// charger and charges at [`SimulatorConfig::home_pose`]. A shelf returns to the pose it
+/// had when the simulator first saw it.
+pub struct Simulator {
+    fake: FakeKachaka,
+    config: SimulatorConfig,
+    shelf_homes: HashMap<String, kachaka_api::Pose>,
+    current: Option<(String, VecDeque<Action>)>,
+    battery_percentage: f64,
+}
+
+impl Simulator {
+    pub fn new(fake: FakeKachaka, config: SimulatorConfig) -> Self {
+        fake.set_auto_complete(false);
+        let battery_percentage = fake.battery_info().remaining_percentage;
+        Self {
+            fake,
+            config,
+            shelf_homes: HashMap::new(),
+            current: None,
+            battery_percentage,
+        }
+    }
+
+    /// Runs the simulation in real time (scaled by `time_scale`) on a background task.
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.config.tick);
+            loop {
+                interval.tick().await;
+                self.step(self.config.tick.as_secs_f64() * self.config.time_scale);
+            }
+        })
+    }
+
+    /// Advances the simulation by `dt` simulated seconds.
+    pub fn step(&mut self, dt: f64) {
+        self.register_shelf_homes();
+        self.sync_running_command();
+        let moving = self.advance_command(dt);
+        self.update_battery(dt, moving);
+    }
+
+    fn register_shelf_homes(&mut self) {
+        for shelf in self.fake.shelves() {
+            if let Some(pose) = shelf.pose {
+                self.shelf_homes.entry(shelf.id).or_insert(pose);
+            }
+        }
+    }
+
+    fn sync_running_command(&mut self) {
+        match (self.fake.running_command(), &self.current) {
+            (Some((command_id, _)), Some((current_id, _))) if &command_id == current_id => {}
+            (Some((command_id, command)), _) => {
+                let actions = self.plan(&command);
+                self.current = Some((command_id, actions));
+            }
+            (None, _) => self.current = None,
+        }
+    }
+
+    fn advance_command(&mut self, dt: f64) -> bool {
+        let Some((command_id, mut actions)) = self.current.take() else {
+            return false;
+        };
+        let before = self.fake.robot_pose();
+        let result = match actions.front_mut() {
+            None => Some(Ok(())),
+            Some(action) => match self.execute(action, dt) {
+                Progress::Running => None,
+                Progress::Done => {
+                    actions.pop_front();
+                    actions.is_empty().then_some(Ok(()))
+                }
+                Progress::Failed(error_code) => Some(Err(KachakaError { error_code })),
+            },
+        };
+        match result {
+            Some(result) => {
+                self.fake.complete_command_by_id(&command_id, result);
+            }
+            None => self.current = Some((command_id, actions)),
+        }
+        self.fake.robot_pose() != before
+    }
+
+    fn update_battery(&mut self, dt: f64, moving: bool) {
+        let pose = self.fake.robot_pose();
+        let home = &self.config.home_pose;
+        let at_home =
+            !moving && pose.distance(home) < POSITION_TOLERANCE * 5.0 && self.current.is_none();
+        let (status, rate) = if at_home {
+            (PowerSupplyStatus::Charging, self.config.charge_rate)
+        } else if moving {
+            (
+                PowerSupplyStatus::Discharging,
+                -self.config.moving_discharge_rate,
+            )
+        } else {
+            (
+                PowerSupplyStatus::Discharging,
+                -self.config.idle_discharge_rate,
+            )
+        };
+        self.battery_percentage = (self.battery_percentage + rate * dt).clamp(0.0, 100.0);
+
+        // Publish only noticeable changes so that battery watchers are not flooded.
+        let published = self.fake.battery_info();
+        if published.power_supply_status != status
+            || (published.remaining_percentage - self.battery_percentage).abs() >= 0.1
+        {
+            self.fake.set_battery_info(BatteryInfo {
+                power_supply_status: status,
+                remaining_percentage: self.battery_percentage,
+            });
+        }
+    }
+
+    fn find_location(&self, location_id: &str) -> Option<kachaka_api::Pose> {
+        self.fake
+            .locations()
+            .into_iter()
+            .find(|location| location.id == location_id)
+            .map(|location| location.pose.unwrap_or_default())
+    }
+
+    fn find_shelf(&self, shelf_id: &str) -> Option<kachaka_api::Pose> {
+        self.fake
+            .shelves()
+            .into_iter()
+            .find(|shelf| shelf.id == shelf_id)
+            .map(|shelf| shelf.pose.unwrap_or_default())
+    }
+
+    fn plan(&self, command: &Command) -> VecDeque<Action> {
+        let mut actions = VecDeque::new();
+        if let Err(error_code) = self.plan_into(command, &mut actions) {
+            actions.clear();
+            actions.push_back(Action::Fail { error_code });
+        }
+        actions
+    }
+
+    fn plan_into(&self, command: &Command, actions: &mut VecDeque<Action>) -> Result<(), i32> {
+        match command {
+            Command::MoveShelf {
+                shelf_id,
+                location_id,
+            } => {
+                let destination = self
+                    .find_location(location_id)
+                    .ok_or(LOCATION_NOT_FOUND_ERROR_CODE)?;
+                self.plan_pick_up(shelf_id, actions)?;
+                actions.push_back(navigate(&destination, true));
+                actions.push_back(Action::Place {
+                    pose: Some(destination),
+                });
+            }
+            Command::ReturnShelf { shelf_id } => {
+                let moving_shelf_id = self.fake.moving_shelf_id();
+                let shelf_id = if shelf_id.is_empty() {
+                    &moving_shelf_id
+                } else {
+                    shelf_id
+                };
+                self.plan_return_shelf(shelf_id, actions)?;
+            }
+            Command::UndockShelf { .. } => actions.push_back(Action::Place { pose: None }),
+            Command::MoveToLocation { location_id } => {
+                let destination = self
+                    .find_location(location_id)
+                    .ok_or(LOCATION_NOT_FOUND_ERROR_CODE)?;
+                actions.push_back(navigate(&destination, true));
+            }
+            Command::ReturnHome => {
+                let moving_shelf_id = self.fake.moving_shelf_id();
+                if !moving_shelf_id.is_empty() {
+                    self.plan_return_shelf(&moving_shelf_id, actions)?;
+                }
+                actions.push_back(navigate(&self.config.home_pose.clone().into(), true));
+            }
+            Command::DockShelf => actions.push_back(Action::Dock { shelf_id: None }),
+            Command::Speak { text } => actions.push_back(Action::Wait {
+                remaining: text.chars().count() as f64 * self.config.speak_seconds_per_char,
+            }),
+            Command::MoveToPose { x, y, yaw } => actions.push_back(Action::Navigate {
+                x: *x,
+                y: *y,
+                yaw: Some(*yaw),
+            }),
+            Command::Lock { duration_sec } => actions.push_back(Action::Wait {
+                remaining: *duration_sec,
+            }),
+            Command::MoveForward {
+                distance_meter,
+                speed,
+            } => actions.push_back(Action::Forward {
+                remaining: *distance_meter,
+                speed: if *speed > 0.0 {
+                    *speed
+                } else {
+                    self.config.linear_speed
+                },
+            }),
+            Command::RotateInPlace { angle_radian } => actions.push_back(Action::RotateBy {
+                remaining: *angle_radian,
+            }),
+            Command::DockAnyShelfWithRegistration { location_id, .. } => {
+                let destination = self
+                    .find_location(location_id)
+                    .ok_or(LOCATION_NOT_FOUND_ERROR_CODE)?;
+                actions.push_back(navigate(&destination, true));
+                actions.push_back(Action::Dock { shelf_id: None });
+            }
+        }
+        Ok(())
+    }
+
+    fn plan_pick_up(&self, shelf_id: &str, actions: &mut VecDeque<Action>) -> Result<(), i32> {
+        let shelf_pose = self
+            .find_shelf(shelf_id)
+            .ok_or(SHELF_NOT_FOUND_ERROR_CODE)?;
+        let moving_shelf_id = self.fake.moving_shelf_id();
+        if moving_shelf_id == shelf_id {
+            return Ok(());
+        }
+        if !moving_shelf_id.is_empty() {
+            return Err(SHELF_UNAVAILABLE_ERROR_CODE);
+        }
+        actions.push_back(navigate(&shelf_pose, false));
+        actions.push_back(Action::Dock {
+            shelf_id: Some(shelf_id.to_string()),
+        });
+        Ok(())
+    }
+
+    fn plan_return_shelf(&self, shelf_id: &str, actions: &mut VecDeque<Action>) -> Result<(), i32> {
+        if shelf_id.is_empty() {
+            return Err(SHELF_UNAVAILABLE_ERROR_CODE);
+        }
+        let home = self
+            .shelf_homes
+            .get(shelf_id)
+            .cloned()
+            .ok_or(SHELF_NOT_FOUND_ERROR_CODE)?;
+        self.plan_pick_up(shelf_id, actions)?;
+        actions.push_back(navigate(&home, true));
+        actions.push_back(Action::Place { pose: Some(home) });
+        Ok(())
+    }
+
+    fn execute(&mut self, action: &mut Action, dt: f64) -> Progress {
+        let mut pose = self.fake.robot_pose();
+        let progress = match action {
+            Action::Navigate { x, y, yaw } => {
+                let (dx, dy) = (*x - pose.x, *y - pose.y);
+                let distance = dx.hypot(dy);
+                if distance > POSITION_TOLERANCE {
+                    let heading = dy.atan2(dx);
+                    if !self.rotate_towards(&mut pose, heading, dt) {
+                        let step = distance.min(self.config.linear_speed * dt);
+                        pose.x += step * heading.cos();
+                        pose.y += step * heading.sin();
+                    }
+                    Progress::Running
+                } else {
+                    pose.x = *x;
+                    pose.y = *y;
+                    match yaw {
+                        Some(yaw) if self.rotate_towards(&mut pose, *yaw, dt) => Progress::Running,
+                        _ => Progress::Done,
+                    }
+                }
+            }
+            Action::RotateBy { remaining } => {
+                let step = remaining.abs().min(self.config.angular_speed * dt);
+                let step = step.copysign(*remaining);
+                pose.theta = normalize_angle(pose.theta + step);
+                *remaining -= step;
+                if remaining.abs() > ANGLE_TOLERANCE {
+                    Progress::Running
+                } else {
+                    Progress::Done
+                }
+            }
+            Action::Forward { remaining, speed } => {
+                let step = remaining.abs().min(*speed * dt).copysign(*remaining);
+                pose.x += step * pose.theta.cos();
+                pose.y += step * pose.theta.sin();
+                *remaining -= step;
+                if remaining.abs() > POSITION_TOLERANCE {
+                    Progress::Running
+                } else {
+                    Progress::Done
+                }
+            }
+            Action::Wait { remaining } => {
+                *remaining -= dt;
+                if *remaining > 0.0 {
+                    Progress::Running
+                } else {
+                    Progress::Done
+                }
+            }
+            Action::Dock { shelf_id } => self.dock(shelf_id.take(), &pose),
+            Action::Place { pose: target } => self.place(target.take(), &pose),
+            Action::Fail { error_code } => Progress::Failed(*error_code),
+        };
+        if pose != self.fake.robot_pose() {
+            self.fake.set_robot_pose(pose);
+        }
+        progress
+    }
+
+    /// Turns `pose` towards `theta`; returns `false` once it is already aligned.
+    fn rotate_towards(&self, pose: &mut Pose, theta: f64, dt: f64) -> bool {
+        let difference = angle_difference(theta, pose.theta);
+        if difference.abs() <= ANGLE_TOLERANCE {
+            pose.theta = theta;
+            return false;
+        }
+        let step = difference
+            .abs()
+            .min(self.config.angular_speed * dt)
+            .copysign(difference);
+        pose.theta = normalize_angle(pose.theta + step);
+        true
+    }
+
+    fn dock(&self, shelf_id: Option<String>, pose: &Pose) -> Progress {
+        if !self.fake.moving_shelf_id().is_empty() {
+            return Progress::Failed(SHELF_UNAVAILABLE_ERROR_CODE);
+        }
+        let shelf_id = shelf_id.or_else(|| {
+            self.fake
+                .shelves()
+                .into_iter()
+                .filter_map(|shelf| {
+                    let shelf_pose = shelf.pose.unwrap_or_default();
+                    let distance = (shelf_pose.x - pose.x).hypot(shelf_pose.y - pose.y);
+                    (distance <= self.config.dock_distance).then_some((distance, shelf.id))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, shelf_id)| shelf_id)
+        });
+        match shelf_id {
+            Some(shelf_id) => {
+                self.fake.set_moving_shelf_id(&shelf_id);
+                Progress::Done
+            }
+            None => Progress::Failed(SHELF_UNAVAILABLE_ERROR_CODE),
+        }
+    }
+
+    fn place(&self, target: Option<kachaka_api::Pose>, pose: &Pose) -> Progress {
+        let shelf_id = self.fake.moving_shelf_id();
+        if shelf_id.is_empty() {
+            return Progress::Failed(SHELF_UNAVAILABLE_ERROR_CODE);
+        }
+        let shelf_pose = target.unwrap_or_else(|| pose.clone().into());
+        self.fake
+            .update_shelf(&shelf_id, |shelf| shelf.pose = Some(shelf_pose));
+        self.fake.set_moving_shelf_id("");
+        Progress::Done
+    }
+}
+
+fn navigate(pose: &kachaka_api::Pose, with_yaw: bool) -> Action {
+    Action::Navigate {
+        x: pose.x,
+        y: pose.y,
+        yaw: with_yaw.then_some(pose.theta),
+    }
+}
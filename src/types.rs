@@ -1,6 +1,6 @@
-use crate::kachaka_api;
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct KachakaError {
     pub error_code: i32,
 }
@@ -11,36 +11,136 @@ pub enum KachakaApiError {
     ApiError(KachakaError),
     NullResult,
     JsonParseError(serde_json::Error),
+    /// A compressed camera image could not be decoded.
+    ImageDecodeError(image::ImageError),
 }
 
-#[derive(Debug)]
+/// A value with the cursor it was returned with, to resume watching right after it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Versioned<T> {
+    pub value: T,
+    pub cursor: i64,
+    pub received_at: std::time::SystemTime,
+}
+
+impl<T> Versioned<T> {
+    /// Stamps the value with the current time.
+    pub fn new(value: T, cursor: i64) -> Self {
+        Self {
+            value,
+            cursor,
+            received_at: std::time::SystemTime::now(),
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Versioned<U> {
+        Versioned {
+            value: f(self.value),
+            cursor: self.cursor,
+            received_at: self.received_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Pose {
     pub x: f64,
     pub y: f64,
     pub theta: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum PowerSupplyStatus {
     Charging,
     Discharging,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct BatteryInfo {
     pub power_supply_status: PowerSupplyStatus,
     pub remaining_percentage: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Command {
+    MoveShelf {
+        shelf_id: String,
+        location_id: String,
+    },
+    ReturnShelf {
+        shelf_id: String,
+    },
+    UndockShelf {
+        shelf_id: String,
+    },
+    MoveToLocation {
+        location_id: String,
+    },
+    ReturnHome,
+    DockShelf,
+    Speak {
+        text: String,
+    },
+    MoveToPose {
+        x: f64,
+        y: f64,
+        yaw: f64,
+    },
+    Lock {
+        duration_sec: f64,
+    },
+    MoveForward {
+        distance_meter: f64,
+        speed: f64,
+    },
+    RotateInPlace {
+        angle_radian: f64,
+    },
+    DockAnyShelfWithRegistration {
+        location_id: String,
+        dock_forward: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum CommandState {
     Unspecified,
     Pending,
-    Running(kachaka_api::Command, String),
+    Running(Command, String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct CommandResult {
-    pub command: kachaka_api::Command,
+    pub command: Command,
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
     pub result: std::result::Result<(), KachakaError>,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Camera {
+    Front,
+    Back,
+}
@@ -0,0 +1,374 @@
+use futures::stream::StreamExt;
+use image::DynamicImage;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::api_impl;
+use crate::types::Camera;
+use crate::KachakaApiClient;
+
+const DATA_EXTENSION: &str = "mjpeg";
+const INDEX_EXTENSION: &str = "idx";
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(1);
+const JPEG_SOI: [u8; 2] = [0xff, 0xd8];
+
+/// Where and how to split the recorded video.
+///
+/// Each segment is a `<prefix>-<first timestamp>.mjpeg` file of concatenated JPEG
+/// frames, which most players open as raw MJPEG, and a `.idx` text file with one
+/// `<timestamp_nsec> <offset> <length>` line per frame.
+#[derive(Debug, Clone)]
+pub struct VideoWriterConfig {
+    pub directory: PathBuf,
+    pub prefix: String,
+    pub max_segment_bytes: Option<u64>,
+    pub max_segment_duration: Option<Duration>,
+}
+
+impl VideoWriterConfig {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            prefix: "video".to_string(),
+            max_segment_bytes: Some(256 * 1024 * 1024),
+            max_segment_duration: Some(Duration::from_secs(600)),
+        }
+    }
+
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    pub fn max_segment_bytes(mut self, max_segment_bytes: Option<u64>) -> Self {
+        self.max_segment_bytes = max_segment_bytes;
+        self
+    }
+
+    pub fn max_segment_duration(mut self, max_segment_duration: Option<Duration>) -> Self {
+        self.max_segment_duration = max_segment_duration;
+        self
+    }
+}
+
+struct SegmentWriter {
+    data: BufWriter<File>,
+    index: BufWriter<File>,
+    start_nsec: i64,
+    bytes: u64,
+}
+
+/// Writes JPEG frames into rotating segments.
+pub struct VideoWriter {
+    config: VideoWriterConfig,
+    segment: Option<SegmentWriter>,
+    segments: Vec<PathBuf>,
+}
+
+impl VideoWriter {
+    pub fn new(config: VideoWriterConfig) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&config.directory)?;
+        Ok(Self {
+            config,
+            segment: None,
+            segments: Vec::new(),
+        })
+    }
+
+    /// Appends a frame; the index is flushed with every frame, so a segment stays
+    /// readable when the process stops without [`VideoWriter::finish`].
+    pub fn write_frame(&mut self, timestamp_nsec: i64, jpeg: &[u8]) -> std::io::Result<()> {
+        if self
+            .segment
+            .as_ref()
+            .is_some_and(|segment| self.should_rotate(segment, timestamp_nsec, jpeg.len()))
+        {
+            self.finish_segment()?;
+        }
+        if self.segment.is_none() {
+            self.segment = Some(self.open_segment(timestamp_nsec)?);
+        }
+        let segment = self.segment.as_mut().unwrap();
+        segment.data.write_all(jpeg)?;
+        segment.data.flush()?;
+        writeln!(
+            segment.index,
+            "{} {} {}",
+            timestamp_nsec,
+            segment.bytes,
+            jpeg.len()
+        )?;
+        segment.index.flush()?;
+        segment.bytes += jpeg.len() as u64;
+        Ok(())
+    }
+
+    /// Data files of the segments written so far, the last one possibly still open.
+    pub fn segments(&self) -> &[PathBuf] {
+        &self.segments
+    }
+
+    pub fn finish(mut self) -> std::io::Result<Vec<PathBuf>> {
+        self.finish_segment()?;
+        Ok(self.segments)
+    }
+
+    fn should_rotate(&self, segment: &SegmentWriter, timestamp_nsec: i64, length: usize) -> bool {
+        let too_large = self
+            .config
+            .max_segment_bytes
+            .is_some_and(|max| segment.bytes > 0 && segment.bytes + length as u64 > max);
+        let too_long = self.config.max_segment_duration.is_some_and(|max| {
+            timestamp_nsec.saturating_sub(segment.start_nsec) >= max.as_nanos() as i64
+        });
+        too_large || too_long
+    }
+
+    fn open_segment(&mut self, start_nsec: i64) -> std::io::Result<SegmentWriter> {
+        let path = self
+            .config
+            .directory
+            .join(format!("{}-{:020}", self.config.prefix, start_nsec))
+            .with_extension(DATA_EXTENSION);
+        let segment = SegmentWriter {
+            data: BufWriter::new(File::create(&path)?),
+            index: BufWriter::new(File::create(path.with_extension(INDEX_EXTENSION))?),
+            start_nsec,
+            bytes: 0,
+        };
+        self.segments.push(path);
+        Ok(segment)
+    }
+
+    fn finish_segment(&mut self) -> std::io::Result<()> {
+        if let Some(mut segment) = self.segment.take() {
+            segment.data.flush()?;
+            segment.index.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the compressed images of a camera in the background.
+///
+/// Frames are stamped with the ROS header time, or the receive time when the header
+/// is empty. Non-JPEG frames are skipped, and frames are dropped while the disk
+/// cannot keep up.
+pub struct VideoRecorder {
+    subscription: JoinHandle<()>,
+    writer_task: JoinHandle<std::io::Result<Vec<PathBuf>>>,
+}
+
+impl VideoRecorder {
+    pub fn start(
+        kachaka_api_client: &KachakaApiClient,
+        camera: Camera,
+        config: VideoWriterConfig,
+    ) -> std::io::Result<Self> {
+        let mut writer = VideoWriter::new(config)?;
+        let (tx, mut rx) = mpsc::channel::<(i64, Vec<u8>)>(64);
+        let writer_task = tokio::task::spawn_blocking(move || {
+            while let Some((timestamp_nsec, jpeg)) = rx.blocking_recv() {
+                writer.write_frame(timestamp_nsec, &jpeg)?;
+            }
+            writer.finish()
+        });
+
+        let client = kachaka_api_client.client.clone();
+        let subscription = tokio::spawn(async move {
+            loop {
+                let mut images = Box::pin(api_impl::watch_camera_ros_compressed_image_raw(
+                    &client, camera,
+                ));
+                while let Some(Ok(image)) = images.next().await {
+                    if !image.data.starts_with(&JPEG_SOI) {
+                        continue;
+                    }
+                    let timestamp_nsec = image
+                        .header
+                        .map(|header| header.stamp_nsec)
+                        .filter(|stamp_nsec| *stamp_nsec != 0)
+                        .unwrap_or_else(now_nsec);
+                    if let Err(mpsc::error::TrySendError::Closed(_)) =
+                        tx.try_send((timestamp_nsec, image.data))
+                    {
+                        return;
+                    }
+                }
+                tokio::time::sleep(RESUBSCRIBE_INTERVAL).await;
+            }
+        });
+
+        Ok(Self {
+            subscription,
+            writer_task,
+        })
+    }
+
+    /// Stops recording and returns the data files of all segments.
+    pub async fn stop(self) -> std::io::Result<Vec<PathBuf>> {
+        self.subscription.abort();
+        let _ = self.subscription.await;
+        self.writer_task.await.map_err(std::io::Error::other)?
+    }
+}
+
+fn now_nsec() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as i64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameIndex {
+    pub timestamp_nsec: i64,
+    pub offset: u64,
+    pub length: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoFrame {
+    pub timestamp_nsec: i64,
+    pub jpeg: Vec<u8>,
+}
+
+impl VideoFrame {
+    pub fn decode(&self) -> image::ImageResult<DynamicImage> {
+        image::load_from_memory_with_format(&self.jpeg, image::ImageFormat::Jpeg)
+    }
+}
+
+/// One recorded segment. Index lines pointing past the end of the data, as left by
+/// an interrupted recording, are ignored.
+pub struct VideoSegment {
+    path: PathBuf,
+    data: File,
+    frames: Vec<FrameIndex>,
+}
+
+impl VideoSegment {
+    /// Opens a segment from the path of its `.mjpeg` data file.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let data = File::open(&path)?;
+        let data_length = data.metadata()?.len();
+        let index = BufReader::new(File::open(path.with_extension(INDEX_EXTENSION))?);
+        let mut frames = Vec::new();
+        for line in index.lines() {
+            let line = line?;
+            let mut fields = line.split_whitespace().map(str::parse::<i64>);
+            let (Some(Ok(timestamp_nsec)), Some(Ok(offset)), Some(Ok(length))) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                break;
+            };
+            let frame = FrameIndex {
+                timestamp_nsec,
+                offset: offset as u64,
+                length: length as u64,
+            };
+            if frame.offset + frame.length > data_length {
+                break;
+            }
+            frames.push(frame);
+        }
+        Ok(Self { path, data, frames })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn frames(&self) -> &[FrameIndex] {
+        &self.frames
+    }
+
+    pub fn read_frame(&mut self, index: usize) -> std::io::Result<VideoFrame> {
+        let frame = self.frames.get(index).copied().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "frame out of range")
+        })?;
+        self.data.seek(SeekFrom::Start(frame.offset))?;
+        let mut jpeg = vec![0; frame.length as usize];
+        self.data.read_exact(&mut jpeg)?;
+        Ok(VideoFrame {
+            timestamp_nsec: frame.timestamp_nsec,
+            jpeg,
+        })
+    }
+}
+
+/// Reads the segments of one recording in time order.
+pub struct VideoReader {
+    segments: Vec<VideoSegment>,
+}
+
+impl VideoReader {
+    /// Opens every segment written with `prefix` in `directory`.
+    pub fn open(directory: impl AsRef<Path>, prefix: &str) -> std::io::Result<Self> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            let is_segment = path.extension().is_some_and(|ext| ext == DATA_EXTENSION)
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.strip_prefix(prefix))
+                    .and_then(|rest| rest.strip_prefix('-'))
+                    .is_some_and(|start| start.parse::<i64>().is_ok());
+            if is_segment {
+                paths.push(path);
+            }
+        }
+        // zero-padded timestamps sort by name
+        paths.sort();
+        let segments = paths
+            .iter()
+            .map(VideoSegment::open)
+            .collect::<std::io::Result<_>>()?;
+        Ok(Self { segments })
+    }
+
+    pub fn segments(&self) -> &[VideoSegment] {
+        &self.segments
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|segment| segment.frames.len())
+            .sum()
+    }
+
+    /// Timestamps of the first and the last frame.
+    pub fn time_range(&self) -> Option<(i64, i64)> {
+        let mut frames = self.segments.iter().flat_map(|segment| &segment.frames);
+        let first = frames.next()?.timestamp_nsec;
+        Some((
+            first,
+            frames.last().map_or(first, |frame| frame.timestamp_nsec),
+        ))
+    }
+
+    pub fn frames(&mut self) -> impl Iterator<Item = std::io::Result<VideoFrame>> + '_ {
+        self.segments.iter_mut().flat_map(|segment| {
+            (0..segment.frames.len()).map(move |index| segment.read_frame(index))
+        })
+    }
+
+    /// The last frame taken at or before `timestamp_nsec`.
+    pub fn frame_at(&mut self, timestamp_nsec: i64) -> std::io::Result<Option<VideoFrame>> {
+        for segment in self.segments.iter_mut().rev() {
+            let count = segment
+                .frames
+                .partition_point(|frame| frame.timestamp_nsec <= timestamp_nsec);
+            if count > 0 {
+                return segment.read_frame(count - 1).map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,258 @@
+use futures::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::types::{KachakaApiError, Pose};
+use crate::KachakaApiClient;
+
+#[derive(Debug)]
+pub enum ZoneError {
+    IoError(std::io::Error),
+    YamlParseError(serde_yaml::Error),
+    TomlParseError(toml::de::Error),
+    UnsupportedExtension(String),
+    /// The polygon of the zone has fewer than three vertices.
+    InvalidPolygon(String),
+    DuplicateZoneName(String),
+}
+
+/// A named polygon in map coordinates.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Zone {
+    pub name: String,
+    /// Vertices `[x, y]` in order; the polygon is closed implicitly.
+    pub polygon: Vec<(f64, f64)>,
+    /// Report [`ZoneEvent::Dwelled`] once the robot has stayed this long.
+    #[serde(default)]
+    pub dwell_sec: Option<f64>,
+}
+
+impl Zone {
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let mut inside = false;
+        let mut previous = match self.polygon.last() {
+            Some(vertex) => *vertex,
+            None => return false,
+        };
+        for &(vx, vy) in &self.polygon {
+            let (px, py) = previous;
+            if (vy > y) != (py > y) && x < (px - vx) * (y - vy) / (py - vy) + vx {
+                inside = !inside;
+            }
+            previous = (vx, vy);
+        }
+        inside
+    }
+}
+
+/// Zones loaded from a YAML or TOML file.
+///
+/// ```yaml
+/// zones:
+///   - name: kitchen
+///     polygon: [[0.0, 0.0], [3.0, 0.0], [3.0, 2.0], [0.0, 2.0]]
+///     dwell_sec: 10
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ZoneSet {
+    pub zones: Vec<Zone>,
+}
+
+impl ZoneSet {
+    pub fn new(zones: Vec<Zone>) -> Result<Self, ZoneError> {
+        let mut names = HashSet::new();
+        for zone in &zones {
+            if zone.polygon.len() < 3 {
+                return Err(ZoneError::InvalidPolygon(zone.name.clone()));
+            }
+            if !names.insert(zone.name.as_str()) {
+                return Err(ZoneError::DuplicateZoneName(zone.name.clone()));
+            }
+        }
+        Ok(Self { zones })
+    }
+
+    pub fn from_yaml_str(s: &str) -> Result<Self, ZoneError> {
+        let zone_set: Self = serde_yaml::from_str(s).map_err(ZoneError::YamlParseError)?;
+        Self::new(zone_set.zones)
+    }
+
+    pub fn from_toml_str(s: &str) -> Result<Self, ZoneError> {
+        let zone_set: Self = toml::from_str(s).map_err(ZoneError::TomlParseError)?;
+        Self::new(zone_set.zones)
+    }
+
+    /// Loads zones, choosing the format from the extension (`.yaml`, `.yml` or `.toml`).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ZoneError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(ZoneError::IoError)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => Self::from_yaml_str(&content),
+            Some("toml") => Self::from_toml_str(&content),
+            extension => Err(ZoneError::UnsupportedExtension(
+                extension.unwrap_or_default().to_string(),
+            )),
+        }
+    }
+
+    pub fn zones_at(&self, pose: &Pose) -> Vec<&Zone> {
+        self.zones
+            .iter()
+            .filter(|zone| zone.contains(pose.x, pose.y))
+            .collect()
+    }
+
+    pub fn tracker(&self) -> ZoneTracker {
+        ZoneTracker::new(self.zones.clone())
+    }
+
+    /// Zone events of the robot, from [`KachakaApiClient::watch_robot_pose`].
+    pub async fn watch_zone_events(
+        &self,
+        client: &mut KachakaApiClient,
+    ) -> impl Stream<Item = Result<ZoneEvent, KachakaApiError>> {
+        self.tracker().track(client.watch_robot_pose().await)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZoneEvent {
+    Entered {
+        zone: String,
+        pose: Pose,
+    },
+    Exited {
+        zone: String,
+        pose: Pose,
+        duration: Duration,
+    },
+    /// The robot has been in the zone for its `dwell_sec`; sent once per visit.
+    Dwelled {
+        zone: String,
+        pose: Pose,
+        duration: Duration,
+    },
+}
+
+struct Visit {
+    entered_at: Instant,
+    dwelled: bool,
+}
+
+/// Turns poses into [`ZoneEvent`]s. The first pose enters every zone containing it.
+pub struct ZoneTracker {
+    zones: Vec<Zone>,
+    visits: Vec<Option<Visit>>,
+    last_pose: Option<Pose>,
+}
+
+impl ZoneTracker {
+    pub fn new(zones: Vec<Zone>) -> Self {
+        let visits = zones.iter().map(|_| None).collect();
+        Self {
+            zones,
+            visits,
+            last_pose: None,
+        }
+    }
+
+    pub fn update(&mut self, pose: &Pose, now: Instant) -> Vec<ZoneEvent> {
+        let mut events = Vec::new();
+        for (zone, visit) in self.zones.iter().zip(self.visits.iter_mut()) {
+            let inside = zone.contains(pose.x, pose.y);
+            match visit {
+                None if inside => {
+                    *visit = Some(Visit {
+                        entered_at: now,
+                        dwelled: false,
+                    });
+                    events.push(ZoneEvent::Entered {
+                        zone: zone.name.clone(),
+                        pose: pose.clone(),
+                    });
+                }
+                Some(current) if !inside => {
+                    events.push(ZoneEvent::Exited {
+                        zone: zone.name.clone(),
+                        pose: pose.clone(),
+                        duration: now.saturating_duration_since(current.entered_at),
+                    });
+                    *visit = None;
+                }
+                _ => {}
+            }
+        }
+        self.last_pose = Some(pose.clone());
+        events.extend(self.check_dwell(now));
+        events
+    }
+
+    /// Dwell events that are due at `now`, also when the pose has not changed.
+    pub fn check_dwell(&mut self, now: Instant) -> Vec<ZoneEvent> {
+        let Some(pose) = &self.last_pose else {
+            return Vec::new();
+        };
+        let mut events = Vec::new();
+        for (zone, visit) in self.zones.iter().zip(self.visits.iter_mut()) {
+            let (Some(visit), Some(dwell_sec)) = (visit, zone.dwell_sec) else {
+                continue;
+            };
+            let duration = now.saturating_duration_since(visit.entered_at);
+            if !visit.dwelled && duration.as_secs_f64() >= dwell_sec {
+                visit.dwelled = true;
+                events.push(ZoneEvent::Dwelled {
+                    zone: zone.name.clone(),
+                    pose: pose.clone(),
+                    duration,
+                });
+            }
+        }
+        events
+    }
+
+    /// When the next dwell event is due, if any.
+    pub fn next_dwell_deadline(&self) -> Option<Instant> {
+        self.zones
+            .iter()
+            .zip(&self.visits)
+            .filter_map(|(zone, visit)| {
+                let visit = visit.as_ref().filter(|visit| !visit.dwelled)?;
+                Some(visit.entered_at + Duration::from_secs_f64(zone.dwell_sec?))
+            })
+            .min()
+    }
+
+    /// Zone events for a stream of poses; errors are passed through.
+    pub fn track<E>(
+        self,
+        poses: impl Stream<Item = Result<Pose, E>> + Send + 'static,
+    ) -> impl Stream<Item = Result<ZoneEvent, E>> {
+        let state = (self, Box::pin(poses), VecDeque::new());
+        stream::unfold(state, |(mut tracker, mut poses, mut pending)| async move {
+            loop {
+                if let Some(event) = pending.pop_front() {
+                    return Some((Ok(event), (tracker, poses, pending)));
+                }
+                let deadline = tracker.next_dwell_deadline();
+                tokio::select! {
+                    item = poses.next() => match item? {
+                        Ok(pose) => pending.extend(tracker.update(&pose, Instant::now())),
+                        Err(error) => return Some((Err(error), (tracker, poses, pending))),
+                    },
+                    _ = sleep_until(deadline) => {
+                        pending.extend(tracker.check_dwell(Instant::now()));
+                    }
+                }
+            }
+        })
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
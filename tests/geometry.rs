@@ -0,0 +1,92 @@
+use std::f64::consts::PI;
+
+use kachaka_api::geometry::{angle_difference, normalize_angle};
+use kachaka_api::Pose;
+use proptest::prelude::*;
+
+const EPSILON: f64 = 1e-6;
+
+fn angle() -> impl Strategy<Value = f64> {
+    -10.0 * PI..10.0 * PI
+}
+
+fn pose() -> impl Strategy<Value = Pose> {
+    (-100.0..100.0, -100.0..100.0, angle()).prop_map(|(x, y, theta)| Pose::new(x, y, theta))
+}
+
+fn assert_pose_eq(a: &Pose, b: &Pose) {
+    assert!(
+        (a.x - b.x).abs() < EPSILON
+            && (a.y - b.y).abs() < EPSILON
+            && angle_difference(a.theta, b.theta).abs() < EPSILON,
+        "{:?} != {:?}",
+        a,
+        b
+    );
+}
+
+proptest! {
+    #[test]
+    fn normalized_angle_is_in_range_and_equivalent(a in angle()) {
+        let normalized = normalize_angle(a);
+        prop_assert!(-PI < normalized && normalized <= PI);
+        prop_assert!((a.sin() - normalized.sin()).abs() < EPSILON);
+        prop_assert!((a.cos() - normalized.cos()).abs() < EPSILON);
+        prop_assert!((normalize_angle(normalized) - normalized).abs() < EPSILON);
+    }
+
+    #[test]
+    fn angle_difference_is_shortest(to in angle(), from in angle()) {
+        let difference = angle_difference(to, from);
+        prop_assert!(-PI < difference && difference <= PI);
+        prop_assert!(angle_difference(from + difference, to).abs() < EPSILON);
+    }
+
+    #[test]
+    fn compose_is_associative(a in pose(), b in pose(), c in pose()) {
+        assert_pose_eq(&a.compose(&b).compose(&c), &a.compose(&b.compose(&c)));
+    }
+
+    #[test]
+    fn identity_is_neutral(a in pose()) {
+        assert_pose_eq(&a.compose(&Pose::identity()), &a);
+        assert_pose_eq(&Pose::identity().compose(&a), &a);
+    }
+
+    #[test]
+    fn inverse_cancels(a in pose()) {
+        assert_pose_eq(&a.compose(&a.inverse()), &Pose::identity());
+        assert_pose_eq(&a.inverse().compose(&a), &Pose::identity());
+        assert_pose_eq(&a.inverse().inverse(), &a);
+    }
+
+    #[test]
+    fn relative_pose_round_trips(a in pose(), origin in pose()) {
+        assert_pose_eq(&origin.compose(&a.relative_to(&origin)), &a);
+    }
+
+    #[test]
+    fn compose_preserves_distance(a in pose(), b in pose(), frame in pose()) {
+        let distance = frame.compose(&a).distance(&frame.compose(&b));
+        prop_assert!((distance - a.distance(&b)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn distance_is_a_metric(a in pose(), b in pose(), c in pose()) {
+        prop_assert!(a.distance(&a) == 0.0);
+        prop_assert_eq!(a.distance(&b), b.distance(&a));
+        prop_assert!(a.distance(&c) <= a.distance(&b) + b.distance(&c) + EPSILON);
+    }
+
+    #[test]
+    fn interpolation_hits_endpoints(a in pose(), b in pose(), t in 0.0..1.0) {
+        assert_pose_eq(&a.interpolate(&b, 0.0), &a);
+        assert_pose_eq(&a.interpolate(&b, 1.0), &b);
+        let middle = a.interpolate(&b, t);
+        prop_assert!((a.distance(&middle) - a.distance(&b) * t).abs() < EPSILON);
+        prop_assert!(
+            angle_difference(middle.theta, a.theta).abs()
+                <= angle_difference(b.theta, a.theta).abs() + EPSILON
+        );
+    }
+}